@@ -1,14 +1,89 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+};
 
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use num_enum::{FromPrimitive, IntoPrimitive};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::gp::GpFlags;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Update {
+    /// Process ID of the R session which sent this, to tell sessions apart.
+    pub pid: u32,
     pub globals: Globals,
+    /// The objects passed to `visualize`, in order.
+    pub roots: Vec<Root>,
     pub sexprecs: Vec<Sexprec>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Root {
+    /// The argument name or the expression used to pass the object.
+    pub name: String,
+    pub sexp: Sexp,
+}
+
+impl Update {
+    /// Check the update is internally consistent.
+    ///
+    /// Visualizr shouldn't trust the other side - it could be an older inspectr
+    /// or it could have read garbage while walking corrupted memory.
+    #[must_use]
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = Vec::new();
+
+        if self.sexprecs.is_empty() {
+            issues.push(ValidationIssue::Empty);
+        }
+
+        let mut addresses = HashSet::new();
+        for sexprec in &self.sexprecs {
+            if !addresses.insert(sexprec.address) {
+                issues.push(ValidationIssue::DuplicateAddress(sexprec.address));
+            }
+        }
+
+        if self.roots.is_empty() {
+            issues.push(ValidationIssue::NoRoots);
+        }
+        for root in &self.roots {
+            if !addresses.contains(&root.sexp) {
+                issues.push(ValidationIssue::MissingRoot(root.sexp));
+            }
+        }
+
+        for sexprec in &self.sexprecs {
+            if !sexprec.payload.fits(sexprec.ty) {
+                issues.push(ValidationIssue::TypeMismatch {
+                    address: sexprec.address,
+                    ty: sexprec.ty,
+                    payload: sexprec.payload.name(),
+                });
+            }
+
+            // The walker doesn't follow any pointers of unknown types, not even attrib.
+            if sexprec.ty.is_unknown() {
+                continue;
+            }
+            let mut ptrs = vec![("attrib", sexprec.attrib)];
+            ptrs.extend(sexprec.payload.pointers());
+            for (field, dest) in ptrs {
+                if !addresses.contains(&dest) {
+                    issues.push(ValidationIssue::DanglingPointer {
+                        src: sexprec.address,
+                        field,
+                        dest,
+                    });
+                }
+            }
+        }
+
+        ValidationReport { issues }
+    }
+}
+
 impl Display for Update {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         for sexprec in &self.sexprecs {
@@ -19,6 +94,106 @@ impl Display for Update {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    Empty,
+    NoRoots,
+    MissingRoot(Sexp),
+    DuplicateAddress(Sexp),
+    DanglingPointer {
+        src: Sexp,
+        field: &'static str,
+        dest: Sexp,
+    },
+    TypeMismatch {
+        address: Sexp,
+        ty: Sexptype,
+        payload: &'static str,
+    },
+}
+
+impl ValidationIssue {
+    /// Fatal issues mean there's nothing sensible to draw.
+    #[must_use]
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            ValidationIssue::Empty | ValidationIssue::NoRoots | ValidationIssue::MissingRoot(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    #[must_use]
+    pub fn is_fatal(&self) -> bool {
+        self.issues.iter().any(ValidationIssue::is_fatal)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReportFormatter<'a>(pub &'a Globals, pub &'a ValidationReport);
+
+impl Display for ReportFormatter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let globals = self.0;
+        let report = self.1;
+        if report.is_ok() {
+            return writeln!(f, "update is valid");
+        }
+
+        writeln!(f, "update has {} issue(s):", report.issues.len())?;
+        for issue in &report.issues {
+            match issue {
+                ValidationIssue::Empty => {
+                    writeln!(f, "no sexps")?;
+                }
+                ValidationIssue::NoRoots => {
+                    writeln!(f, "no roots")?;
+                }
+                ValidationIssue::MissingRoot(root) => {
+                    writeln!(f, "root {} is missing", globals.fmt_ptr(*root))?;
+                }
+                ValidationIssue::DuplicateAddress(address) => {
+                    writeln!(f, "duplicate address {}", globals.fmt_ptr(*address))?;
+                }
+                ValidationIssue::DanglingPointer { src, field, dest } => {
+                    writeln!(
+                        f,
+                        "dangling pointer {}.{} -> {}",
+                        globals.fmt_ptr(*src),
+                        field,
+                        globals.fmt_ptr(*dest)
+                    )?;
+                }
+                ValidationIssue::TypeMismatch {
+                    address,
+                    ty,
+                    payload,
+                } => {
+                    writeln!(
+                        f,
+                        "{} has type {} but payload {}",
+                        globals.fmt_ptr(*address),
+                        ty,
+                        payload
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct SexpFormatter<'a>(pub &'a Globals, pub &'a Sexprec);
 
@@ -33,6 +208,12 @@ impl Display for SexpFormatter<'_> {
             sexprec.ty_name,
             sexprec.ty,
         )?;
+        if sexprec.ty.is_unknown() {
+            writeln!(
+                f,
+                "WARNING: unknown SEXP type (newer R version or corrupted memory?)"
+            )?;
+        }
 
         writeln!(f, "sxpinfo: {:#066b}", sexprec.sxpinfo_bits)?;
         // named and extra are 16 bits so 5 digits is exactly enough,
@@ -58,46 +239,11 @@ impl Display for SexpFormatter<'_> {
             sexprec.sxpinfo.extra,
         )?;
 
-        // GP:
-        // 0: DDVAL / HASHASH / READY_TO_FINALIZE       |
-        // 1: BYTES / FINALIZE_ON_EXIT                  | MISSING
-        // 2: LATIN1                                    |
-        // 3: UTF8                                      |
-        // 4: S4
-        // 5: NOJIT / GROWABLE / CACHED
-        // 6: ASCII
-        // 7:
-        // 8:
-        // 9:
-        // 10:
-        // 11: ASSIGNMENT_PENDING
-        // 12: SPECIAL_SYMBOL / NO_SPECIAL_SYMBOLS
-        // 13: BASE_SYM_CACHED
-        // 14: BINDING_LOCK / FRAME_LOCK
-        // 15: ACTIVE_BINDING / GLOBAL_FRAME
-
-        // ENC_KNOWN = LATIN1_MASK | UTF8_MASK
-        // ENVFLAGS / PRSEEN / LEVELS / ARGUSED / OLDTYPE = GP
-        // PRSEEN: R-ints says only bit 0
-        // GROWABLE is true only if (&& XLENGTH(x) < XTRUELENGTH(x))
-        // Latin-1, UTF-8 or ASCII + cached - only CHARSXP according to R-ints
-        // check inspect.c - some bits only apply for some types
-
-        // TODO recheck all the bits and masks before release
-
-        writeln!(f, "         GP bit meaning: |< MISSING [0:3] >|                                   ASSIGNMENT_PENDING")?;
-        writeln!(f, "                         DDVAL / HASHASH / READY_TO_FINALIZE                   |    SPECIAL_SYMBOL / NO_SPECIAL_SYMBOLS")?;
-        writeln!(f, "                         |    BYTES / FINALIZE_ON_EXIT                         |    |    BASE_SYM_CACHED")?;
-        writeln!(f, "                         |    |    LATIN1         NOJIT / GROWABLE / CACHED    |    |    |    BINDING_LOCK / FRAME_LOCK")?;
-        writeln!(f, "                         |    |    |    UTF8 S4   |    ASCII                   |    |    |    |    ACTIVE_BINDING / GLOBAL_FRAME")?;
-        writeln!(f, "                  index: 0    1    2    3    4    5    6    7    8    9   10   11   12   13   14   15")?;
-        write!(f, "                  value: ")?;
-        for index in 0..16 {
-            let bit = sexprec.sxpinfo.gp & (1 << index);
-            let is_set = bit >> index; // Shift it so only 0 or 1 remains
-            write!(f, "{:<5}", is_set)?;
-        }
-        writeln!(f)?;
+        writeln!(
+            f,
+            "gp flags: {}",
+            GpFlags::new(sexprec.ty, sexprec.sxpinfo.gp)
+        )?;
 
         writeln!(f, "attrib {}", globals.fmt_ptr(sexprec.attrib))?;
         writeln!(
@@ -118,6 +264,20 @@ impl Display for SexpFormatter<'_> {
             SexpPayload::Vecsxp(vecsxp) => {
                 write!(f, "length: {:<35}", vecsxp.length)?;
                 write!(f, "truelength: {:<35}", vecsxp.truelength)?;
+                if !vecsxp.values.is_empty() {
+                    writeln!(f)?;
+                    write!(f, "values: {}", vecsxp.values.join(", "))?;
+                }
+                for elt in &vecsxp.elements {
+                    writeln!(f)?;
+                    write!(f, "elt: {}", globals.fmt_ptr(*elt))?;
+                }
+            }
+            SexpPayload::Charsxp(charsxp) => {
+                write!(f, "length: {:<35}", charsxp.length)?;
+                write!(f, "truelength: {:<35}", charsxp.truelength)?;
+                writeln!(f)?;
+                write!(f, "string: {:?}", charsxp.string)?;
             }
             SexpPayload::Primsxp(primsxp) => {
                 write!(f, "offset: {:<35}", primsxp.offset)?;
@@ -147,13 +307,72 @@ impl Display for SexpFormatter<'_> {
                 write!(f, "expr: {:35}", globals.fmt_ptr(promsxp.expr))?;
                 write!(f, "env: {:35}", globals.fmt_ptr(promsxp.env))?;
             }
+            SexpPayload::Unknown => {
+                write!(f, "unknown payload - pointers not followed")?;
+            }
         }
 
         Ok(())
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A few lines with the most important parts of the SEXPREC,
+/// for when there isn't enough space for the whole `SexpFormatter` dump.
+#[derive(Debug)]
+pub struct SexpSummary<'a>(pub &'a Globals, pub &'a Sexprec);
+
+impl Display for SexpSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let globals = self.0;
+        let sexprec = &self.1;
+        writeln!(f, "{} {}", sexprec.ty, globals.fmt_ptr(sexprec.address))?;
+        match &sexprec.payload {
+            SexpPayload::Vecsxp(vecsxp) => {
+                writeln!(f, "length: {}", vecsxp.length)?;
+                if !vecsxp.values.is_empty() {
+                    writeln!(f, "{}", truncate(&vecsxp.values.join(", "), 40))?;
+                }
+            }
+            SexpPayload::Charsxp(charsxp) => {
+                writeln!(f, "{:?}", truncate(&charsxp.string, 40))?;
+            }
+            SexpPayload::Primsxp(primsxp) => {
+                writeln!(f, "offset: {}", primsxp.offset)?;
+            }
+            SexpPayload::Unknown => {
+                writeln!(f, "unknown payload")?;
+            }
+            _ => {
+                for (name, sexp) in sexprec.payload.pointers() {
+                    writeln!(f, "{}: {}", name, globals.fmt_ptr(sexp))?;
+                }
+            }
+        }
+        if sexprec.attrib != globals.nil_value {
+            writeln!(f, "attrib: {}", globals.fmt_ptr(sexprec.attrib))?;
+        }
+        write!(
+            f,
+            "named: {}, gp: {}",
+            sexprec.sxpinfo.named,
+            GpFlags::new(sexprec.ty, sexprec.sxpinfo.gp)
+        )
+    }
+}
+
+/// Shorten `s` to at most `max` chars, marking where it was cut.
+#[must_use]
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_owned()
+    } else {
+        let mut short: String = s.chars().take(max.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Globals {
     pub unbound_value: Sexp,
     pub nil_value: Sexp,
@@ -174,68 +393,49 @@ impl Globals {
     pub fn fmt_ptr(&self, sexp: Sexp) -> String {
         // Don't impl Display for Sexp so it's impossible to accidentally forget to use this function.
         let mut s = format!("@{:x}", sexp.0);
-        if sexp == self.unbound_value {
-            s.push_str(" (R_UnboundValue)");
-        } else if sexp == self.nil_value {
-            s.push_str(" (R_NilValue)");
-        } else if sexp == self.missing_arg {
-            s.push_str(" (R_MissingArg)");
-        } else if sexp == self.global_env {
-            s.push_str(" (R_GlobalEnv)");
-        } else if sexp == self.empty_env {
-            s.push_str(" (R_EmptyEnv)");
-        } else if sexp == self.base_env {
-            s.push_str(" (R_BaseEnv)");
-        } else if sexp == self.base_namespace {
-            s.push_str(" (R_BaseNamespace)");
-        } else if sexp == self.namespace_registry {
-            s.push_str(" (R_NamespaceRegistry)");
-        } else if sexp == self.src_ref {
-            s.push_str(" (R_Srcref)");
-        } else if sexp == self.in_bc_interpreter {
-            s.push_str(" (R_InBCInterpreter)");
-        } else if sexp == self.current_expression {
-            s.push_str(" (R_CurrentExpression)");
+        if let Some(name) = self.name(sexp) {
+            s.push_str(&format!(" ({})", name));
         }
-        // else if sexp == self.restart_token {
-        //     s.push_str(" (R_RestartToken)");
-        // }
         s
     }
 
+    /// The name of the global variable holding `sexp`, e.g. "R_NilValue".
+    #[must_use]
+    pub fn name(&self, sexp: Sexp) -> Option<&'static str> {
+        self.values()
+            .into_iter()
+            .find(|&(value, _)| value == sexp)
+            .map(|(_, name)| name)
+    }
+
+    /// The inverse of `name`.
+    #[must_use]
+    pub fn by_name(&self, name: &str) -> Option<Sexp> {
+        self.values()
+            .into_iter()
+            .find(|&(_, n)| n == name)
+            .map(|(value, _)| value)
+    }
+
+    fn values(&self) -> [(Sexp, &'static str); 11] {
+        [
+            (self.unbound_value, "R_UnboundValue"),
+            (self.nil_value, "R_NilValue"),
+            (self.missing_arg, "R_MissingArg"),
+            (self.global_env, "R_GlobalEnv"),
+            (self.empty_env, "R_EmptyEnv"),
+            (self.base_env, "R_BaseEnv"),
+            (self.base_namespace, "R_BaseNamespace"),
+            (self.namespace_registry, "R_NamespaceRegistry"),
+            (self.src_ref, "R_Srcref"),
+            (self.in_bc_interpreter, "R_InBCInterpreter"),
+            (self.current_expression, "R_CurrentExpression"),
+            //(self.restart_token, "R_RestartToken"),
+        ]
+    }
+
     pub fn is_global(&self, sexp: Sexp) -> bool {
-        let values = [
-            self.unbound_value,
-            self.nil_value,
-            self.missing_arg,
-            self.global_env,
-            self.empty_env,
-            self.base_env,
-            self.base_namespace,
-            self.namespace_registry,
-            self.src_ref,
-            self.in_bc_interpreter,
-            self.current_expression,
-        ];
-        // let names = [
-        //     "R_UnboundValue",
-        //     "R_NilValue",
-        //     "R_MissingArg",
-        //     "R_GlobalEnv",
-        //     "R_EmptyEnv",
-        //     "R_BaseEnv",
-        //     "R_BaseNamespace",
-        //     "R_NamespaceRegistry",
-        //     "R_Srcref",
-        //     "R_InBCInterpreter",
-        //     "R_CurrentExpression",
-        // ];
-        for value in values {
-            if sexp == value {
-                return true;
-            }
-        }
-        false
+        self.name(sexp).is_some()
     }
 }
 
@@ -243,7 +443,7 @@ impl Globals {
 // inspectr and visualizr could be running on different architectures.
 // LATER What about integers? Need sufficient size for all architectures supported by R.
 //      What about signed vs unsigned char?
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Sexp(pub u64);
 
 impl<T> From<*mut T> for Sexp {
@@ -252,7 +452,9 @@ impl<T> From<*mut T> for Sexp {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, IntoPrimitive, TryFromPrimitive)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, IntoPrimitive, FromPrimitive,
+)]
 #[repr(i32)]
 pub enum Sexptype {
     NILSXP = 0,
@@ -282,6 +484,16 @@ pub enum Sexptype {
     S4SXP = 25,
     NEWSXP = 30,
     FREESXP = 31,
+    /// Anything R shouldn't produce - either a newer R version or corrupted memory.
+    #[num_enum(catch_all)]
+    Unknown(i32),
+}
+
+impl Sexptype {
+    #[must_use]
+    pub fn is_unknown(self) -> bool {
+        matches!(self, Sexptype::Unknown(_))
+    }
 }
 
 impl Display for Sexptype {
@@ -290,7 +502,7 @@ impl Display for Sexptype {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Sxpinfo {
     pub ty: i32,
     pub scalar: i32,
@@ -307,22 +519,81 @@ pub struct Sxpinfo {
     pub extra: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum SexpPayload {
     Vecsxp(Vecsxp),
+    Charsxp(Charsxp),
     Primsxp(Primsxp),
     Symsxp(Symsxp),
     Listsxp(Listsxp),
     Envsxp(Envsxp),
     Closxp(Closxp),
     Promsxp(Promsxp),
+    /// Used for unknown types - we don't know the layout so we don't read anything.
+    Unknown,
+}
+
+impl SexpPayload {
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            SexpPayload::Vecsxp(_) => "Vecsxp",
+            SexpPayload::Charsxp(_) => "Charsxp",
+            SexpPayload::Primsxp(_) => "Primsxp",
+            SexpPayload::Symsxp(_) => "Symsxp",
+            SexpPayload::Listsxp(_) => "Listsxp",
+            SexpPayload::Envsxp(_) => "Envsxp",
+            SexpPayload::Closxp(_) => "Closxp",
+            SexpPayload::Promsxp(_) => "Promsxp",
+            SexpPayload::Unknown => "Unknown",
+        }
+    }
+
+    /// Whether this is the payload inspectr produces for `ty`.
+    #[must_use]
+    pub fn fits(&self, ty: Sexptype) -> bool {
+        match ty {
+            Sexptype::SYMSXP => matches!(self, SexpPayload::Symsxp(_)),
+            Sexptype::CLOSXP => matches!(self, SexpPayload::Closxp(_)),
+            Sexptype::ENVSXP => matches!(self, SexpPayload::Envsxp(_)),
+            Sexptype::PROMSXP => matches!(self, SexpPayload::Promsxp(_)),
+            Sexptype::SPECIALSXP | Sexptype::BUILTINSXP => {
+                matches!(self, SexpPayload::Primsxp(_))
+            }
+            Sexptype::CHARSXP => matches!(self, SexpPayload::Charsxp(_)),
+            Sexptype::LGLSXP
+            | Sexptype::INTSXP
+            | Sexptype::REALSXP
+            | Sexptype::CPLXSXP
+            | Sexptype::STRSXP
+            | Sexptype::VECSXP
+            | Sexptype::EXPRSXP
+            | Sexptype::RAWSXP => matches!(self, SexpPayload::Vecsxp(_)),
+            // Types without a specific layout are read as lists.
+            Sexptype::NILSXP
+            | Sexptype::LISTSXP
+            | Sexptype::LANGSXP
+            | Sexptype::DOTSXP
+            | Sexptype::ANYSXP
+            | Sexptype::BCODESXP
+            | Sexptype::EXTPTRSXP
+            | Sexptype::WEAKREFSXP
+            | Sexptype::S4SXP
+            | Sexptype::NEWSXP
+            | Sexptype::FREESXP => matches!(self, SexpPayload::Listsxp(_)),
+            Sexptype::Unknown(_) => matches!(self, SexpPayload::Unknown),
+        }
+    }
 }
 
 #[rustfmt::skip]
 impl SexpPayload {
     pub fn pointers(&self) -> Vec<(&'static str, Sexp)> {
         match &self {
-            SexpPayload::Vecsxp(_vecsxp) => {
+            SexpPayload::Vecsxp(vecsxp) => {
+                vecsxp.elements.iter().map(|&elt| ("elt", elt)).collect()
+            }
+            SexpPayload::Charsxp(_charsxp) => {
                 vec![]
             }
             SexpPayload::Primsxp(_primsxp) => {
@@ -363,57 +634,76 @@ impl SexpPayload {
                     ("env", promsxp.env),
                 ]
             }
+            SexpPayload::Unknown => {
+                vec![]
+            }
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Vecsxp {
     pub length: i64,
     pub truelength: i64,
+    /// STRSXP, VECSXP and EXPRSXP elements - only the first few, `length` says how many there are.
+    /// Empty for ALTREP vectors, their data isn't where it normally is.
+    pub elements: Vec<Sexp>,
+    /// The first few values of atomic vectors, formatted like R prints them (e.g. `TRUE`, `1L`, `NA`).
+    pub values: Vec<String>,
+}
+
+/// CHARSXPs are vectors too but we also want the contents.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Charsxp {
+    /// The full length in bytes, `string` may be cut off.
+    pub length: i64,
+    pub truelength: i64,
+    /// At most the first few hundred bytes.
+    /// Invalid UTF-8 (e.g. latin1 or bytes encoding) is replaced by U+FFFD.
+    pub string: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Primsxp {
     pub offset: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Symsxp {
     pub pname: Sexp,
     pub value: Sexp,
     pub internal: Sexp,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Listsxp {
     pub carval: Sexp,
     pub cdrval: Sexp,
     pub tagval: Sexp,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Envsxp {
     pub frame: Sexp,
     pub enclos: Sexp,
     pub hashtab: Sexp,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Closxp {
     pub formals: Sexp,
     pub body: Sexp,
     pub env: Sexp,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Promsxp {
     pub value: Sexp,
     pub expr: Sexp,
     pub env: Sexp,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Sexprec {
     pub address: Sexp,
     pub ty: Sexptype,
@@ -425,3 +715,119 @@ pub struct Sexprec {
     pub gengc_prev_node: Sexp,
     pub payload: SexpPayload,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn issues(update: &Update) -> Vec<ValidationIssue> {
+        update.validate().issues
+    }
+
+    #[test]
+    fn valid() {
+        let report = pairlist().validate();
+        assert!(report.is_ok());
+        assert!(!report.is_fatal());
+    }
+
+    #[test]
+    fn empty() {
+        let update = update(&[], Vec::new());
+        assert_eq!(
+            issues(&update),
+            [ValidationIssue::Empty, ValidationIssue::NoRoots]
+        );
+        assert!(update.validate().is_fatal());
+    }
+
+    #[test]
+    fn no_roots() {
+        let mut update = pairlist();
+        update.roots.clear();
+        assert_eq!(issues(&update), [ValidationIssue::NoRoots]);
+        assert!(update.validate().is_fatal());
+    }
+
+    #[test]
+    fn missing_root() {
+        let mut update = pairlist();
+        update.roots[0].sexp = Sexp(0x999);
+        assert_eq!(issues(&update), [ValidationIssue::MissingRoot(Sexp(0x999))]);
+        assert!(update.validate().is_fatal());
+    }
+
+    #[test]
+    fn duplicate_address() {
+        let mut update = pairlist();
+        update.sexprecs.push(real(0x200, &["2"]));
+        assert_eq!(
+            issues(&update),
+            [ValidationIssue::DuplicateAddress(Sexp(0x200))]
+        );
+        assert!(!update.validate().is_fatal());
+    }
+
+    #[test]
+    fn dangling_pointer() {
+        let mut update = pairlist();
+        update.sexprecs[0].attrib = Sexp(0x999);
+        assert_eq!(
+            issues(&update),
+            [ValidationIssue::DanglingPointer {
+                src: Sexp(0x100),
+                field: "attrib",
+                dest: Sexp(0x999),
+            }]
+        );
+        assert!(!update.validate().is_fatal());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let mut update = pairlist();
+        update.sexprecs[1].ty = Sexptype::CLOSXP;
+        assert_eq!(
+            issues(&update),
+            [ValidationIssue::TypeMismatch {
+                address: Sexp(0x200),
+                ty: Sexptype::CLOSXP,
+                payload: "Vecsxp",
+            }]
+        );
+        assert!(!update.validate().is_fatal());
+    }
+
+    #[test]
+    fn unknown_types_are_not_followed() {
+        let mut update = pairlist();
+        update.sexprecs[1] = sexprec(0x200, Sexptype::Unknown(42), SexpPayload::Unknown);
+        update.sexprecs[1].attrib = Sexp(0x999);
+        assert!(update.validate().is_ok());
+    }
+
+    #[test]
+    fn only_empty_and_roots_are_fatal() {
+        let fatal = [
+            ValidationIssue::Empty,
+            ValidationIssue::NoRoots,
+            ValidationIssue::MissingRoot(Sexp(1)),
+        ];
+        let non_fatal = [
+            ValidationIssue::DuplicateAddress(Sexp(1)),
+            ValidationIssue::DanglingPointer {
+                src: Sexp(1),
+                field: "attrib",
+                dest: Sexp(2),
+            },
+            ValidationIssue::TypeMismatch {
+                address: Sexp(1),
+                ty: Sexptype::NILSXP,
+                payload: "Unknown",
+            },
+        ];
+        assert!(fatal.iter().all(ValidationIssue::is_fatal));
+        assert!(!non_fatal.iter().any(ValidationIssue::is_fatal));
+    }
+}
@@ -0,0 +1,387 @@
+//! Comparing two updates - what was added, removed and what changed in the nodes both have.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    data::{Globals, Sexp, SexpPayload, Sexprec, Update},
+    gp::GpFlags,
+    graph::ObjectGraph,
+};
+
+/// How to decide which old node corresponds to which new node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Matching {
+    /// Same address - shows what R modified in place and what it copied.
+    Address,
+    /// Same position in the graph - the same path of fields from the same root.
+    /// Shows how two objects differ even if they share no memory,
+    /// e.g. why `identical()` says they're not the same.
+    Structure,
+}
+
+impl Matching {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Matching::Address => "by address",
+            Matching::Structure => "by structure",
+        }
+    }
+}
+
+/// One field which differs between the old and the new node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// E.g. `named`, `gp bits` or `carval`.
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Same,
+    Changed(Vec<Change>),
+    /// Only in the new update.
+    Added,
+    /// Only in the old update.
+    Removed,
+}
+
+#[derive(Debug)]
+pub struct Diff {
+    pub matching: Matching,
+    /// All nodes of the new update followed by the removed nodes of the old one.
+    ///
+    /// Removed nodes keep their old pointers so edges to other removed nodes are kept,
+    /// LATER edges from removed nodes to matched nodes are lost in structural mode
+    /// and a removed node is dropped if the new update reuses its address.
+    pub merged: Update,
+    /// The status of every node in `merged`.
+    pub statuses: HashMap<Sexp, Status>,
+    /// Which old node each new node was matched with.
+    pub old_addresses: HashMap<Sexp, Sexp>,
+}
+
+impl Diff {
+    #[must_use]
+    pub fn new(old: &Update, new: &Update, matching: Matching) -> Self {
+        let old_graph = ObjectGraph::new(old.clone());
+        let new_graph = ObjectGraph::new(new.clone());
+        let matched = match matching {
+            Matching::Address => match_addresses(&old_graph, &new_graph),
+            Matching::Structure => match_structure(&old_graph, &new_graph),
+        };
+        // What an old pointer should be in the new update if nothing changed.
+        let translate = |sexp: Sexp| match matched.get(&sexp) {
+            Some(&new_sexp) => new_sexp,
+            None => translate_global(&old.globals, &new.globals, sexp),
+        };
+
+        let mut merged = new.clone();
+        let mut statuses = HashMap::new();
+        let mut old_addresses = HashMap::new();
+        let new_to_old: HashMap<_, _> = matched.iter().map(|(&o, &n)| (n, o)).collect();
+        for sexprec in &new.sexprecs {
+            let status = match new_to_old.get(&sexprec.address) {
+                Some(&old_sexp) => {
+                    old_addresses.insert(sexprec.address, old_sexp);
+                    let old_rec = old_graph.get(old_sexp).unwrap();
+                    let changes = compare(old, new, old_rec, sexprec, translate);
+                    if changes.is_empty() {
+                        Status::Same
+                    } else {
+                        Status::Changed(changes)
+                    }
+                }
+                None => Status::Added,
+            };
+            statuses.entry(sexprec.address).or_insert(status);
+        }
+        for sexprec in &old.sexprecs {
+            if !matched.contains_key(&sexprec.address) && !statuses.contains_key(&sexprec.address) {
+                statuses.insert(sexprec.address, Status::Removed);
+                merged.sexprecs.push(sexprec.clone());
+            }
+        }
+
+        Self {
+            matching,
+            merged,
+            statuses,
+            old_addresses,
+        }
+    }
+}
+
+fn match_addresses(old: &ObjectGraph, new: &ObjectGraph) -> HashMap<Sexp, Sexp> {
+    old.sexprecs()
+        .iter()
+        .map(|sexprec| sexprec.address)
+        .filter(|&sexp| new.contains(sexp))
+        .map(|sexp| (sexp, sexp))
+        .collect()
+}
+
+/// Walk both graphs in parallel from the roots, pairing nodes reached through the same field.
+///
+/// Roots are paired in order. Globals are only paired with the same global
+/// (by name, the addresses differ between R sessions).
+/// Each node is paired at most once, the first path to reach it wins.
+fn match_structure(old: &ObjectGraph, new: &ObjectGraph) -> HashMap<Sexp, Sexp> {
+    let mut matched = HashMap::new();
+    let mut used = HashSet::new();
+    let mut queue: VecDeque<_> = old
+        .roots()
+        .iter()
+        .zip(new.roots())
+        .map(|(o, n)| (o.sexp, n.sexp))
+        .collect();
+    while let Some((o, n)) = queue.pop_front() {
+        if !old.contains(o) || !new.contains(n) || matched.contains_key(&o) || used.contains(&n) {
+            continue;
+        }
+        if old.globals().name(o) != new.globals().name(n) {
+            continue;
+        }
+        matched.insert(o, n);
+        used.insert(n);
+        for old_edge in old.outgoing(o) {
+            if let Some(new_edge) = new.outgoing(n).iter().find(|e| e.kind == old_edge.kind) {
+                queue.push_back((old_edge.dest, new_edge.dest));
+            }
+        }
+    }
+    matched
+}
+
+/// Globals are the same object in every session even if the address differs.
+fn translate_global(old: &Globals, new: &Globals, sexp: Sexp) -> Sexp {
+    old.name(sexp)
+        .and_then(|name| new.by_name(name))
+        .unwrap_or(sexp)
+}
+
+/// Field by field comparison, pointers are equal if `translate(old) == new`.
+fn compare(
+    old: &Update,
+    new: &Update,
+    o: &Sexprec,
+    n: &Sexprec,
+    translate: impl Fn(Sexp) -> Sexp,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut field = |field: &str, old: String, new: String| {
+        if old != new {
+            changes.push(Change {
+                field: field.to_owned(),
+                old,
+                new,
+            });
+        }
+    };
+
+    field("type", o.ty.to_string(), n.ty.to_string());
+
+    let (oi, ni) = (&o.sxpinfo, &n.sxpinfo);
+    field("scalar", oi.scalar.to_string(), ni.scalar.to_string());
+    field("obj", oi.obj.to_string(), ni.obj.to_string());
+    field("alt", oi.alt.to_string(), ni.alt.to_string());
+    field("mark", oi.mark.to_string(), ni.mark.to_string());
+    field("debug", oi.debug.to_string(), ni.debug.to_string());
+    field("trace", oi.trace.to_string(), ni.trace.to_string());
+    field("spare", oi.spare.to_string(), ni.spare.to_string());
+    field("gcgen", oi.gcgen.to_string(), ni.gcgen.to_string());
+    field("gccls", oi.gccls.to_string(), ni.gccls.to_string());
+    field("named", oi.named.to_string(), ni.named.to_string());
+    field("extra", oi.extra.to_string(), ni.extra.to_string());
+    if oi.gp != ni.gp {
+        let (old_gp, new_gp) = (GpFlags::new(o.ty, oi.gp), GpFlags::new(n.ty, ni.gp));
+        field("gp", old_gp.to_string(), new_gp.to_string());
+        // The raw bits too, not every bit has a name.
+        field(
+            "gp bits",
+            format!("{:#018b}", old_gp.gp),
+            format!("{:#018b}", new_gp.gp),
+        );
+    }
+
+    let ptr = |globals: &Globals, sexp: Sexp| globals.fmt_ptr(sexp);
+    if translate(o.attrib) != n.attrib {
+        field(
+            "attrib",
+            ptr(&old.globals, o.attrib),
+            ptr(&new.globals, n.attrib),
+        );
+    }
+
+    match (&o.payload, &n.payload) {
+        (SexpPayload::Vecsxp(ov), SexpPayload::Vecsxp(nv)) => {
+            field("length", ov.length.to_string(), nv.length.to_string());
+            field(
+                "truelength",
+                ov.truelength.to_string(),
+                nv.truelength.to_string(),
+            );
+            field("values", ov.values.join(", "), nv.values.join(", "));
+        }
+        (SexpPayload::Charsxp(oc), SexpPayload::Charsxp(nc)) => {
+            field("length", oc.length.to_string(), nc.length.to_string());
+            field(
+                "truelength",
+                oc.truelength.to_string(),
+                nc.truelength.to_string(),
+            );
+            field(
+                "string",
+                format!("{:?}", oc.string),
+                format!("{:?}", nc.string),
+            );
+        }
+        (SexpPayload::Primsxp(op), SexpPayload::Primsxp(np)) => {
+            field("offset", op.offset.to_string(), np.offset.to_string());
+        }
+        _ => {}
+    }
+
+    let old_ptrs = o.payload.pointers();
+    let new_ptrs = n.payload.pointers();
+    for i in 0..old_ptrs.len().max(new_ptrs.len()) {
+        match (old_ptrs.get(i), new_ptrs.get(i)) {
+            (Some(&(name, op)), Some(&(_, np))) => {
+                if translate(op) != np {
+                    field(name, ptr(&old.globals, op), ptr(&new.globals, np));
+                }
+            }
+            (Some(&(name, op)), None) => field(name, ptr(&old.globals, op), "-".to_owned()),
+            (None, Some(&(name, np))) => field(name, "-".to_owned(), ptr(&new.globals, np)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    const BOTH: [Matching; 2] = [Matching::Address, Matching::Structure];
+
+    fn status(diff: &Diff, address: u64) -> &Status {
+        &diff.statuses[&Sexp(address)]
+    }
+
+    fn changes(diff: &Diff, address: u64) -> &[Change] {
+        match status(diff, address) {
+            Status::Changed(changes) => changes,
+            other => panic!("{:#x} is {:?}", address, other),
+        }
+    }
+
+    /// `pairlist(1, "a")` somewhere else in memory, e.g. a deep copy or another session.
+    fn moved_pairlist() -> Update {
+        update(
+            &[("x", 0x1100)],
+            vec![
+                list(0x1100, Sexp(0x1200), Sexp(0x1300), NIL),
+                real(0x1200, &["1"]),
+                list(0x1300, Sexp(0x1400), NIL, NIL),
+                charsxp(0x1400, "a"),
+                nil(),
+            ],
+        )
+    }
+
+    #[test]
+    fn same() {
+        for matching in BOTH {
+            let diff = Diff::new(&pairlist(), &pairlist(), matching);
+            assert_eq!(diff.merged.sexprecs.len(), 5);
+            assert!(diff.statuses.values().all(|s| *s == Status::Same));
+        }
+    }
+
+    #[test]
+    fn changed() {
+        let mut new = pairlist();
+        new.sexprecs[1] = real(0x200, &["2"]);
+        for matching in BOTH {
+            let diff = Diff::new(&pairlist(), &new, matching);
+            let expected = Change {
+                field: "values".to_owned(),
+                old: "1".to_owned(),
+                new: "2".to_owned(),
+            };
+            assert_eq!(changes(&diff, 0x200), [expected]);
+            assert_eq!(status(&diff, 0x100), &Status::Same);
+        }
+    }
+
+    #[test]
+    fn changed_gp() {
+        let mut new = pairlist();
+        // UTF8_MASK
+        new.sexprecs[3].sxpinfo.gp = 8;
+        for matching in BOTH {
+            let diff = Diff::new(&pairlist(), &new, matching);
+            let changes = changes(&diff, 0x400);
+            assert_eq!(changes.len(), 2);
+            assert_eq!(
+                (changes[0].old.as_str(), changes[0].new.as_str()),
+                ("-", "UTF8")
+            );
+            assert_eq!(changes[1].field, "gp bits");
+            assert_eq!(changes[1].old, "0b0000000000000000");
+            assert_eq!(changes[1].new, "0b0000000000001000");
+        }
+    }
+
+    #[test]
+    fn added() {
+        let mut new = pairlist();
+        new.sexprecs[2] = list(0x300, Sexp(0x400), Sexp(0x500), NIL);
+        new.sexprecs.push(real(0x500, &["2"]));
+        for matching in BOTH {
+            let diff = Diff::new(&pairlist(), &new, matching);
+            assert_eq!(status(&diff, 0x500), &Status::Added);
+            assert_eq!(changes(&diff, 0x300)[0].field, "cdrval");
+            assert_eq!(diff.merged.sexprecs.len(), 6);
+        }
+    }
+
+    #[test]
+    fn removed() {
+        let mut new = pairlist();
+        new.sexprecs[0] = list(0x100, Sexp(0x200), NIL, NIL);
+        new.sexprecs
+            .retain(|s| ![0x300, 0x400].contains(&s.address.0));
+        for matching in BOTH {
+            let diff = Diff::new(&pairlist(), &new, matching);
+            assert_eq!(status(&diff, 0x300), &Status::Removed);
+            assert_eq!(status(&diff, 0x400), &Status::Removed);
+            assert_eq!(changes(&diff, 0x100)[0].field, "cdrval");
+            // Removed nodes are added back so they can be shown.
+            assert_eq!(diff.merged.sexprecs.len(), 5);
+        }
+    }
+
+    #[test]
+    fn moved() {
+        // Nothing in common by address except R_NilValue.
+        let diff = Diff::new(&pairlist(), &moved_pairlist(), Matching::Address);
+        for address in [0x1100, 0x1200, 0x1300, 0x1400] {
+            assert_eq!(status(&diff, address), &Status::Added);
+        }
+        for address in [0x100, 0x200, 0x300, 0x400] {
+            assert_eq!(status(&diff, address), &Status::Removed);
+        }
+
+        // The same object by structure.
+        let diff = Diff::new(&pairlist(), &moved_pairlist(), Matching::Structure);
+        assert!(diff.statuses.values().all(|s| *s == Status::Same));
+        assert_eq!(diff.old_addresses[&Sexp(0x1400)], Sexp(0x400));
+        assert_eq!(diff.merged.sexprecs.len(), 5);
+    }
+}
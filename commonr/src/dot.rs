@@ -0,0 +1,343 @@
+//! Graphviz DOT export for documentation and slides, e.g. `dot -Tsvg x.dot > x.svg`.
+//!
+//! Every node is a record with a port for each pointer field at the bottom,
+//! edges leave from the port of the field they're stored in.
+//! Globals which are only pointed to (not roots) are grey octagons with just their name,
+//! their contents are never exported - almost everything can reach R_GlobalEnv.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Write,
+};
+
+use crate::{
+    data::{truncate, Sexp, SexpPayload, Sexprec, Update},
+    gp::GpFlags,
+    graph::{Edge, EdgeKind, ObjectGraph},
+};
+
+/// How many vector values to show, the rest is only counted.
+const MAX_VALUES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Only export nodes at most this many edges away from a root, None for everything.
+    /// Nodes at the limit which have more children are dashed.
+    pub max_depth: Option<usize>,
+    /// Show `named` and the GP flags.
+    pub sxpinfo: bool,
+    /// Show vector values, strings and symbol names.
+    pub values: bool,
+    /// Include attributes.
+    pub attrib: bool,
+    /// Include pointers to globals like R_NilValue. Without them,
+    /// the diagram shows the structure more clearly but e.g. the end of a pairlist isn't visible.
+    pub globals: bool,
+    /// Pointer fields to leave out, e.g. `enclos` to not export parent environments.
+    pub skip: HashSet<String>,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            sxpinfo: false,
+            values: true,
+            attrib: true,
+            globals: true,
+            skip: HashSet::new(),
+        }
+    }
+}
+
+#[must_use]
+pub fn to_dot(update: &Update, options: &DotOptions) -> String {
+    graph_to_dot(&ObjectGraph::new(update.clone()), options)
+}
+
+/// The output only depends on the graph and options (nodes are in the order they were received)
+/// so it can be diffed and checked into git.
+#[must_use]
+pub fn graph_to_dot(graph: &ObjectGraph, options: &DotOptions) -> String {
+    let (included, truncated) = included(graph, options);
+    let roots: HashSet<_> = graph.roots().iter().map(|root| root.sexp).collect();
+    // Globals which are roots are exported like other nodes, the rest are just placeholders.
+    let placeholder = |sexp: Sexp| graph.is_global(sexp) && !roots.contains(&sexp);
+
+    let mut out = String::new();
+    writeln!(out, "digraph update {{").unwrap();
+    writeln!(
+        out,
+        "    node [shape=record, fontname=\"monospace\", fontsize=10];"
+    )
+    .unwrap();
+    writeln!(out, "    edge [fontname=\"monospace\", fontsize=9];").unwrap();
+
+    for (i, root) in graph.roots().iter().enumerate() {
+        if included.contains(&root.sexp) {
+            writeln!(
+                out,
+                "    root{} [shape=plaintext, label=\"{}\"];",
+                i,
+                escape(&root.name)
+            )
+            .unwrap();
+            writeln!(out, "    root{} -> {} [style=dotted];", i, id(root.sexp)).unwrap();
+        }
+    }
+
+    for sexprec in graph.sexprecs() {
+        let sexp = sexprec.address;
+        if !included.contains(&sexp) {
+            continue;
+        }
+        if placeholder(sexp) {
+            let name = graph.globals().name(sexp).unwrap();
+            writeln!(
+                out,
+                "    {} [shape=octagon, style=filled, fillcolor=lightgrey, label=\"{}\"];",
+                id(sexp),
+                name
+            )
+            .unwrap();
+            continue;
+        }
+
+        let mut attributes = vec![format!(
+            "label=\"{}\"",
+            record_label(graph, sexprec, options)
+        )];
+        let mut styles = Vec::new();
+        if graph.is_global(sexp) {
+            styles.push("filled");
+            attributes.push("fillcolor=lightgrey".to_owned());
+        }
+        if truncated.contains(&sexp) {
+            styles.push("dashed");
+        }
+        if !styles.is_empty() {
+            attributes.push(format!("style=\"{}\"", styles.join(",")));
+        }
+        writeln!(out, "    {} [{}];", id(sexp), attributes.join(", ")).unwrap();
+    }
+
+    for sexprec in graph.sexprecs() {
+        let sexp = sexprec.address;
+        if !included.contains(&sexp) || placeholder(sexp) {
+            continue;
+        }
+        for edge in graph.outgoing(sexp) {
+            if follows(graph, edge, options) && included.contains(&edge.dest) {
+                writeln!(
+                    out,
+                    "    {}:{} -> {} [label=\"{}\"];",
+                    id(sexp),
+                    port(edge.kind),
+                    id(edge.dest),
+                    edge.name
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Breadth first from the roots so `max_depth` is the length of the shortest path.
+///
+/// Returns the exported nodes and the ones whose children were cut off by `max_depth`.
+fn included(graph: &ObjectGraph, options: &DotOptions) -> (HashSet<Sexp>, HashSet<Sexp>) {
+    let mut included = HashSet::new();
+    let mut truncated = HashSet::new();
+    let mut queue: VecDeque<_> = graph.roots().iter().map(|root| (root.sexp, 0)).collect();
+    while let Some((sexp, depth)) = queue.pop_front() {
+        if !graph.contains(sexp) || !included.insert(sexp) {
+            continue;
+        }
+        // Only roots are expanded, not globals we got to from them.
+        if depth > 0 && graph.is_global(sexp) {
+            continue;
+        }
+        let edges = graph
+            .outgoing(sexp)
+            .iter()
+            .filter(|edge| follows(graph, edge, options));
+        if options.max_depth == Some(depth) {
+            if edges.clone().any(|edge| !included.contains(&edge.dest)) {
+                truncated.insert(sexp);
+            }
+            continue;
+        }
+        queue.extend(edges.map(|edge| (edge.dest, depth + 1)));
+    }
+    (included, truncated)
+}
+
+fn follows(graph: &ObjectGraph, edge: &Edge, options: &DotOptions) -> bool {
+    if edge.kind == EdgeKind::Attrib && !options.attrib {
+        return false;
+    }
+    if !options.globals && graph.is_global(edge.dest) {
+        return false;
+    }
+    !options.skip.contains(edge.name)
+}
+
+/// `{ header | values | sxpinfo | { ports } }` - the ports are in a row at the bottom.
+fn record_label(graph: &ObjectGraph, sexprec: &Sexprec, options: &DotOptions) -> String {
+    let sexp = sexprec.address;
+    let mut header = format!("{} {}", sexprec.ty, graph.globals().fmt_ptr(sexp));
+    if options.values {
+        if let Some(name) = graph.symbol_name(sexp) {
+            header.push_str(&format!(" `{}`", truncate(name, 20)));
+        }
+    }
+    let mut rows = vec![escape_record(&header)];
+
+    if options.values {
+        let values = match &sexprec.payload {
+            SexpPayload::Vecsxp(vecsxp) if !vecsxp.values.is_empty() => {
+                let shown: Vec<_> = vecsxp.values.iter().take(MAX_VALUES).cloned().collect();
+                let mut values = shown.join(", ");
+                if vecsxp.length > shown.len() as i64 {
+                    values.push_str(&format!(", … ({} total)", vecsxp.length));
+                }
+                Some(values)
+            }
+            SexpPayload::Charsxp(charsxp) => Some(format!("{:?}", truncate(&charsxp.string, 30))),
+            SexpPayload::Primsxp(primsxp) => Some(format!("offset {}", primsxp.offset)),
+            _ => None,
+        };
+        if let Some(values) = values {
+            rows.push(escape_record(&values));
+        }
+    }
+
+    if options.sxpinfo {
+        let info = &sexprec.sxpinfo;
+        rows.push(escape_record(&format!(
+            "named {}  gp {}",
+            info.named,
+            GpFlags::new(sexprec.ty, info.gp)
+        )));
+    }
+
+    let mut ports = Vec::new();
+    if options.attrib && !options.skip.contains("attrib") {
+        ports.push(format!("<{}> attrib", port(EdgeKind::Attrib)));
+    }
+    for (i, (name, _)) in sexprec.payload.pointers().into_iter().enumerate() {
+        if !options.skip.contains(name) {
+            ports.push(format!("<{}> {}", port(EdgeKind::Payload(i)), name));
+        }
+    }
+    if !ports.is_empty() {
+        rows.push(format!("{{{}}}", ports.join("|")));
+    }
+
+    format!("{{{}}}", rows.join("|"))
+}
+
+fn id(sexp: Sexp) -> String {
+    format!("n{:x}", sexp.0)
+}
+
+fn port(kind: EdgeKind) -> String {
+    match kind {
+        EdgeKind::Attrib => "attrib".to_owned(),
+        EdgeKind::Payload(i) => format!("p{}", i),
+    }
+}
+
+/// For quoted strings.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Record labels also give meaning to braces, bars and angle brackets.
+fn escape_record(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    /// `pairlist(1, "a", 2)` with characters that need escaping.
+    fn escaped() -> Update {
+        let mut update = pairlist();
+        update.roots[0].name = "x \"y\"".to_owned();
+        update.sexprecs[2] = list(0x300, Sexp(0x400), Sexp(0x500), NIL);
+        update.sexprecs[3] = charsxp(0x400, "{a|b} <c> \\");
+        update
+            .sexprecs
+            .push(list(0x500, Sexp(0x600), NIL, Sexp(0x700)));
+        update.sexprecs.push(real(0x600, &["2"]));
+        update
+    }
+
+    #[test]
+    fn golden() {
+        let options = DotOptions {
+            max_depth: Some(2),
+            sxpinfo: true,
+            skip: ["tagval".to_owned()].into_iter().collect(),
+            ..DotOptions::default()
+        };
+        let dot = to_dot(&escaped(), &options);
+        assert_eq!(dot, include_str!("../testdata/pairlist.dot"));
+    }
+
+    #[test]
+    fn everything() {
+        let dot = to_dot(&escaped(), &DotOptions::default());
+        // Nothing is cut off.
+        assert!(!dot.contains("dashed"));
+        assert!(dot.contains(r#"n500:p1 -> n2 [label="cdrval"];"#));
+        // Not in the update, not exported.
+        assert!(!dot.contains("n700"));
+    }
+
+    #[test]
+    fn skip_attrib() {
+        let mut options = DotOptions::default();
+        options.skip.insert("attrib".to_owned());
+        let dot = to_dot(&pairlist(), &options);
+        assert!(!dot.contains("attrib"));
+
+        let options = DotOptions {
+            attrib: false,
+            ..DotOptions::default()
+        };
+        assert_eq!(to_dot(&pairlist(), &options), dot);
+    }
+
+    #[test]
+    fn no_globals() {
+        let options = DotOptions {
+            globals: false,
+            ..DotOptions::default()
+        };
+        let dot = to_dot(&pairlist(), &options);
+        assert!(!dot.contains("R_NilValue"));
+        assert!(!dot.contains("-> n2 "));
+        // The ports are still there, only the edges are left out.
+        assert!(dot.contains("<p1> cdrval"));
+    }
+}
@@ -0,0 +1,305 @@
+//! Decoding of the 16 general purpose (GP) bits in sxpinfo.
+//!
+//! The meaning of the bits depends on the type of the SEXP,
+//! e.g. bit 0 is HASHASH on a CHARSXP but DDVAL on a SYMSXP.
+//! The masks are from Defn.h and memory.c - check inspect.c when updating them.
+//!
+//! Notes:
+//! - ENVFLAGS / PRSEEN / LEVELS / ARGUSED / OLDTYPE all access the whole GP field.
+//! - PRSEEN: R-ints says only bit 0 is used but eval.c also sets it to 2.
+//! - GROWABLE is only true if also XLENGTH(x) < XTRUELENGTH(x), we only show the bit.
+//! - ENC_KNOWN = LATIN1_MASK | UTF8_MASK
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::data::Sexptype;
+
+/// One meaning of one or more GP bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpFlag {
+    pub name: &'static str,
+    pub mask: u16,
+    /// The masked bits shifted down so single bit flags are 0 or 1.
+    pub value: u16,
+}
+
+impl GpFlag {
+    #[must_use]
+    pub fn is_set(&self) -> bool {
+        self.value != 0
+    }
+
+    #[must_use]
+    pub fn is_single_bit(&self) -> bool {
+        self.mask.count_ones() == 1
+    }
+}
+
+impl Display for GpFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_single_bit() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}={}", self.name, self.value)
+        }
+    }
+}
+
+/// The GP bits of a SEXP interpreted according to its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpFlags {
+    pub ty: Sexptype,
+    pub gp: u16,
+}
+
+// Masks shared by multiple types
+const S4_OBJECT: (&str, u16) = ("S4", 1 << 4);
+const BINDING_LOCK: (&str, u16) = ("BINDING_LOCK", 1 << 14);
+const ACTIVE_BINDING: (&str, u16) = ("ACTIVE_BINDING", 1 << 15);
+
+#[rustfmt::skip]
+const CHARSXP_MASKS: &[(&str, u16)] = &[
+    ("HASHASH", 1 << 0),
+    ("BYTES", 1 << 1),
+    ("LATIN1", 1 << 2),
+    ("UTF8", 1 << 3),
+    S4_OBJECT,
+    ("CACHED", 1 << 5),
+    ("ASCII", 1 << 6),
+];
+
+// Symbols in the base env store their binding directly in the symbol,
+// so they can have the binding bits too.
+#[rustfmt::skip]
+const SYMSXP_MASKS: &[(&str, u16)] = &[
+    ("DDVAL", 1 << 0),
+    S4_OBJECT,
+    ("SPECIAL_SYMBOL", 1 << 12),
+    ("BASE_SYM_CACHED", 1 << 13),
+    BINDING_LOCK,
+    ACTIVE_BINDING,
+];
+
+// Pairlist cells - MISSING is used in argument lists,
+// the rest in frames (environment bindings).
+#[rustfmt::skip]
+const LISTSXP_MASKS: &[(&str, u16)] = &[
+    ("MISSING", 0b1111),
+    S4_OBJECT,
+    ("ASSIGNMENT_PENDING", 1 << 11),
+    BINDING_LOCK,
+    ACTIVE_BINDING,
+];
+
+#[rustfmt::skip]
+const DOTSXP_MASKS: &[(&str, u16)] = &[
+    ("MISSING", 0b1111),
+    S4_OBJECT,
+];
+
+#[rustfmt::skip]
+const ENVSXP_MASKS: &[(&str, u16)] = &[
+    S4_OBJECT,
+    ("NO_SPECIAL_SYMBOLS", 1 << 12),
+    ("FRAME_LOCK", 1 << 14),
+    ("GLOBAL_FRAME", 1 << 15),
+];
+
+#[rustfmt::skip]
+const CLOSXP_MASKS: &[(&str, u16)] = &[
+    S4_OBJECT,
+    ("NOJIT", 1 << 5),
+    ("MAYBEJIT", 1 << 6),
+];
+
+// PRSEEN uses the whole GP field.
+#[rustfmt::skip]
+const PROMSXP_MASKS: &[(&str, u16)] = &[
+    ("PRSEEN", 0xffff),
+];
+
+#[rustfmt::skip]
+const VECTOR_MASKS: &[(&str, u16)] = &[
+    S4_OBJECT,
+    ("GROWABLE", 1 << 5),
+];
+
+#[rustfmt::skip]
+const WEAKREFSXP_MASKS: &[(&str, u16)] = &[
+    ("READY_TO_FINALIZE", 1 << 0),
+    ("FINALIZE_ON_EXIT", 1 << 1),
+    S4_OBJECT,
+];
+
+#[rustfmt::skip]
+const DEFAULT_MASKS: &[(&str, u16)] = &[
+    S4_OBJECT,
+];
+
+impl GpFlags {
+    #[must_use]
+    pub fn new(ty: Sexptype, gp: i32) -> Self {
+        // GP is a 16 bit field, the cast only drops the sign extension.
+        Self { ty, gp: gp as u16 }
+    }
+
+    fn masks(&self) -> &'static [(&'static str, u16)] {
+        match self.ty {
+            Sexptype::CHARSXP => CHARSXP_MASKS,
+            Sexptype::SYMSXP => SYMSXP_MASKS,
+            Sexptype::LISTSXP => LISTSXP_MASKS,
+            Sexptype::DOTSXP => DOTSXP_MASKS,
+            Sexptype::ENVSXP => ENVSXP_MASKS,
+            Sexptype::CLOSXP => CLOSXP_MASKS,
+            Sexptype::PROMSXP => PROMSXP_MASKS,
+            Sexptype::LGLSXP
+            | Sexptype::INTSXP
+            | Sexptype::REALSXP
+            | Sexptype::CPLXSXP
+            | Sexptype::STRSXP
+            | Sexptype::VECSXP
+            | Sexptype::EXPRSXP
+            | Sexptype::RAWSXP => VECTOR_MASKS,
+            Sexptype::WEAKREFSXP => WEAKREFSXP_MASKS,
+            Sexptype::NILSXP
+            | Sexptype::LANGSXP
+            | Sexptype::SPECIALSXP
+            | Sexptype::BUILTINSXP
+            | Sexptype::ANYSXP
+            | Sexptype::BCODESXP
+            | Sexptype::EXTPTRSXP
+            | Sexptype::S4SXP
+            | Sexptype::NEWSXP
+            | Sexptype::FREESXP => DEFAULT_MASKS,
+            Sexptype::Unknown(_) => &[],
+        }
+    }
+
+    /// All flags that have a meaning for this type, whether they're set or not.
+    #[must_use]
+    pub fn known(&self) -> Vec<GpFlag> {
+        self.masks()
+            .iter()
+            .map(|&(name, mask)| GpFlag {
+                name,
+                mask,
+                value: (self.gp & mask) >> mask.trailing_zeros(),
+            })
+            .collect()
+    }
+
+    /// Only the flags which are set.
+    #[must_use]
+    pub fn set(&self) -> Vec<GpFlag> {
+        self.known().into_iter().filter(GpFlag::is_set).collect()
+    }
+
+    /// Bits which are set but have no known meaning for this type.
+    #[must_use]
+    pub fn unknown_bits(&self) -> u16 {
+        let known = self.masks().iter().fold(0, |acc, &(_, mask)| acc | mask);
+        self.gp & !known
+    }
+}
+
+impl Display for GpFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let set = self.set();
+        let unknown = self.unknown_bits();
+        if set.is_empty() && unknown == 0 {
+            return write!(f, "-");
+        }
+
+        for (i, flag) in set.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", flag)?;
+        }
+        if unknown != 0 {
+            if !set.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "unknown: {:#018b}", unknown)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_names(ty: Sexptype, gp: i32) -> Vec<&'static str> {
+        GpFlags::new(ty, gp)
+            .set()
+            .iter()
+            .map(|flag| flag.name)
+            .collect()
+    }
+
+    #[test]
+    fn charsxp() {
+        // mkCharCE on an ASCII string marked as UTF-8 - ASCII_MASK | CACHED_MASK | UTF8_MASK
+        let gp = 64 | 32 | 8;
+        assert_eq!(
+            set_names(Sexptype::CHARSXP, gp),
+            ["UTF8", "CACHED", "ASCII"]
+        );
+        let flags = GpFlags::new(Sexptype::CHARSXP, gp);
+        assert_eq!(flags.unknown_bits(), 0);
+        assert_eq!(flags.to_string(), "UTF8 CACHED ASCII");
+
+        // HASHASH and LATIN1, same bits mean something else on a symbol.
+        assert_eq!(set_names(Sexptype::CHARSXP, 0b101), ["HASHASH", "LATIN1"]);
+        assert_eq!(set_names(Sexptype::SYMSXP, 0b1), ["DDVAL"]);
+    }
+
+    #[test]
+    fn envsxp() {
+        // R_GlobalEnv
+        assert_eq!(set_names(Sexptype::ENVSXP, 1 << 15), ["GLOBAL_FRAME"]);
+        // A locked namespace - LockEnvironment sets FRAME_LOCK_MASK,
+        // R_NoSpecialSymbols is set for most package envs.
+        assert_eq!(
+            set_names(Sexptype::ENVSXP, (1 << 14) | (1 << 12)),
+            ["NO_SPECIAL_SYMBOLS", "FRAME_LOCK"]
+        );
+        // The sign extension from reading the bitfield as i32 doesn't matter.
+        let flags = GpFlags::new(Sexptype::ENVSXP, -32768);
+        assert_eq!(flags.gp, 1 << 15);
+        assert_eq!(flags.unknown_bits(), 0);
+    }
+
+    #[test]
+    fn closxp() {
+        assert_eq!(set_names(Sexptype::CLOSXP, 1 << 5), ["NOJIT"]);
+        assert_eq!(set_names(Sexptype::CLOSXP, 1 << 6), ["MAYBEJIT"]);
+        assert_eq!(set_names(Sexptype::CLOSXP, 0), Vec::<&str>::new());
+        assert_eq!(GpFlags::new(Sexptype::CLOSXP, 0).to_string(), "-");
+
+        // Bit 0 means nothing on a closure.
+        let flags = GpFlags::new(Sexptype::CLOSXP, (1 << 5) | 1);
+        assert_eq!(flags.unknown_bits(), 1);
+        assert_eq!(flags.to_string(), "NOJIT unknown: 0b0000000000000001");
+    }
+
+    #[test]
+    fn multi_bit_flags() {
+        let flags = GpFlags::new(Sexptype::LISTSXP, 0b0011);
+        let missing = flags.set()[0];
+        assert_eq!(missing.name, "MISSING");
+        assert!(!missing.is_single_bit());
+        assert_eq!(missing.value, 3);
+        assert_eq!(missing.to_string(), "MISSING=3");
+
+        assert_eq!(GpFlags::new(Sexptype::PROMSXP, 2).to_string(), "PRSEEN=2");
+    }
+
+    #[test]
+    fn unknown_type() {
+        let flags = GpFlags::new(Sexptype::Unknown(42), 0b11);
+        assert!(flags.known().is_empty());
+        assert_eq!(flags.unknown_bits(), 0b11);
+    }
+}
@@ -0,0 +1,715 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use crate::data::{truncate, Globals, Root, Sexp, SexpPayload, Sexprec, Sexptype, Update};
+
+/// Which part of the SEXPREC a pointer is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Attrib,
+    /// Index into `SexpPayload::pointers()`.
+    Payload(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub src: Sexp,
+    pub dest: Sexp,
+    pub kind: EdgeKind,
+    /// Field name, e.g. "carval" or "attrib".
+    pub name: &'static str,
+}
+
+/// How `named` compares to the references we can see in the update.
+///
+/// Since R 4.0, `named` is a reference count - it counts pointers from other objects
+/// (attrib, payload pointers and vector elements), the same things that are edges here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefCount {
+    Matches,
+    /// `named` is higher by this much - the rest of the references come from outside the update
+    /// (e.g. bindings in environments which weren't sent) or R never decremented it.
+    Outside(usize),
+    /// `named` is lower by this much - R doesn't track references from some objects
+    /// (e.g. argument lists) or the object has been marked as shared manually.
+    Untracked(usize),
+}
+
+/// An `Update` indexed for graph traversal.
+///
+/// Only contains edges whose destination is part of the update,
+/// dangling pointers are left to `Update::validate()`.
+/// When an address is duplicated, the first occurrence wins.
+#[derive(Debug)]
+pub struct ObjectGraph {
+    update: Update,
+    indices: HashMap<Sexp, usize>,
+    outgoing: Vec<Vec<Edge>>,
+    incoming: Vec<Vec<Edge>>,
+}
+
+impl ObjectGraph {
+    #[must_use]
+    pub fn new(update: Update) -> Self {
+        Self::with_edges(update, edges_of)
+    }
+
+    fn with_edges(update: Update, mut edges_of: impl FnMut(&Sexprec) -> Vec<Edge>) -> Self {
+        let mut indices = HashMap::new();
+        for (i, sexprec) in update.sexprecs.iter().enumerate() {
+            indices.entry(sexprec.address).or_insert(i);
+        }
+
+        let mut outgoing = vec![Vec::new(); update.sexprecs.len()];
+        let mut incoming = vec![Vec::new(); update.sexprecs.len()];
+        for (i, sexprec) in update.sexprecs.iter().enumerate() {
+            if indices[&sexprec.address] != i {
+                continue; // Duplicate
+            }
+            for edge in edges_of(sexprec) {
+                if let Some(&dest_index) = indices.get(&edge.dest) {
+                    outgoing[i].push(edge);
+                    incoming[dest_index].push(edge);
+                }
+            }
+        }
+
+        Self {
+            update,
+            indices,
+            outgoing,
+            incoming,
+        }
+    }
+
+    /// A smaller graph with only the `kept` nodes.
+    ///
+    /// Paths through removed nodes are replaced by a single edge
+    /// named after the first field on the path, e.g. an environment
+    /// gets a `frame` edge to each closure bound in it.
+    /// Globals are never walked through.
+    #[must_use]
+    pub fn contracted(&self, kept: &HashSet<Sexp>) -> ObjectGraph {
+        let mut update = self.update.clone();
+        update
+            .sexprecs
+            .retain(|sexprec| kept.contains(&sexprec.address));
+
+        Self::with_edges(update, |sexprec| {
+            let mut edges = Vec::new();
+            let mut reached = HashSet::new();
+            for edge in self.outgoing(sexprec.address) {
+                let mut visited = HashSet::new();
+                let mut stack = vec![edge.dest];
+                while let Some(sexp) = stack.pop() {
+                    if !visited.insert(sexp) {
+                        continue;
+                    }
+                    if kept.contains(&sexp) {
+                        if reached.insert(sexp) {
+                            edges.push(Edge {
+                                dest: sexp,
+                                ..*edge
+                            });
+                        }
+                    } else if !self.is_global(sexp) {
+                        stack.extend(self.outgoing(sexp).iter().rev().map(|edge| edge.dest));
+                    }
+                }
+            }
+            edges
+        })
+    }
+
+    #[must_use]
+    pub fn update(&self) -> &Update {
+        &self.update
+    }
+
+    #[must_use]
+    pub fn globals(&self) -> &Globals {
+        &self.update.globals
+    }
+
+    /// In the order they were received, including duplicates.
+    #[must_use]
+    pub fn sexprecs(&self) -> &[Sexprec] {
+        &self.update.sexprecs
+    }
+
+    #[must_use]
+    pub fn roots(&self) -> &[Root] {
+        &self.update.roots
+    }
+
+    #[must_use]
+    pub fn contains(&self, sexp: Sexp) -> bool {
+        self.indices.contains_key(&sexp)
+    }
+
+    #[must_use]
+    pub fn get(&self, sexp: Sexp) -> Option<&Sexprec> {
+        self.indices.get(&sexp).map(|&i| &self.update.sexprecs[i])
+    }
+
+    /// Attrib first, then payload pointers in order.
+    #[must_use]
+    pub fn outgoing(&self, sexp: Sexp) -> &[Edge] {
+        match self.indices.get(&sexp) {
+            Some(&i) => &self.outgoing[i],
+            None => &[],
+        }
+    }
+
+    #[must_use]
+    pub fn incoming(&self, sexp: Sexp) -> &[Edge] {
+        match self.indices.get(&sexp) {
+            Some(&i) => &self.incoming[i],
+            None => &[],
+        }
+    }
+
+    /// Compare the number of incoming edges to `named`, None for globals and unknown nodes.
+    ///
+    /// Globals are referenced from all over the heap so their count means nothing here.
+    #[must_use]
+    pub fn ref_count(&self, sexp: Sexp) -> Option<RefCount> {
+        if self.is_global(sexp) {
+            return None;
+        }
+        let named = usize::try_from(self.get(sexp)?.sxpinfo.named).unwrap_or(0);
+        let in_degree = self.incoming(sexp).len();
+        Some(match named.cmp(&in_degree) {
+            Ordering::Equal => RefCount::Matches,
+            Ordering::Greater => RefCount::Outside(named - in_degree),
+            Ordering::Less => RefCount::Untracked(in_degree - named),
+        })
+    }
+
+    #[must_use]
+    pub fn is_global(&self, sexp: Sexp) -> bool {
+        self.update.globals.is_global(sexp)
+    }
+
+    /// The contents of a CHARSXP.
+    #[must_use]
+    pub fn string_value(&self, sexp: Sexp) -> Option<&str> {
+        match &self.get(sexp)?.payload {
+            SexpPayload::Charsxp(charsxp) => Some(&charsxp.string),
+            _ => None,
+        }
+    }
+
+    /// The name of a SYMSXP, if its pname was sent.
+    #[must_use]
+    pub fn symbol_name(&self, sexp: Sexp) -> Option<&str> {
+        match &self.get(sexp)?.payload {
+            SexpPayload::Symsxp(symsxp) => self.string_value(symsxp.pname),
+            _ => None,
+        }
+    }
+
+    /// A short text to show instead of an address - the name of a symbol
+    /// or the (shortened) contents of a CHARSXP in quotes.
+    #[must_use]
+    pub fn short_name(&self, sexp: Sexp) -> Option<String> {
+        if let Some(name) = self.symbol_name(sexp) {
+            return Some(truncate(name, 20));
+        }
+        self.string_value(sexp)
+            .map(|string| format!("{:?}", truncate(string, 20)))
+    }
+
+    /// Look up an attribute by name - attrib is a pairlist with symbols as tags.
+    ///
+    /// Returns the value (carval) of the first matching cell.
+    #[must_use]
+    pub fn attribute(&self, sexp: Sexp, name: &str) -> Option<Sexp> {
+        let mut cell = self.get(sexp)?.attrib;
+        // The update could contain a cycle, don't loop forever.
+        let mut visited = HashSet::new();
+        while visited.insert(cell) {
+            let sexprec = self.get(cell)?;
+            let listsxp = match (&sexprec.ty, &sexprec.payload) {
+                (Sexptype::LISTSXP, SexpPayload::Listsxp(listsxp)) => listsxp,
+                _ => return None,
+            };
+            if self.symbol_name(listsxp.tagval) == Some(name) {
+                return Some(listsxp.carval);
+            }
+            cell = listsxp.cdrval;
+        }
+        None
+    }
+
+    /// All nodes reachable from `start`, including itself.
+    ///
+    /// Globals are neither included nor followed - almost everything
+    /// can reach R_NilValue or R_GlobalEnv so they'd only add noise.
+    #[must_use]
+    pub fn reachable(&self, start: Sexp) -> HashSet<Sexp> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(sexp) = stack.pop() {
+            if !self.contains(sexp) || self.is_global(sexp) || !visited.insert(sexp) {
+                continue;
+            }
+            stack.extend(self.outgoing(sexp).iter().map(|edge| edge.dest));
+        }
+        visited
+    }
+
+    /// Whether `to` can be reached from `from`, following all edges including through globals.
+    #[must_use]
+    pub fn reaches(&self, from: Sexp, to: Sexp) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(sexp) = stack.pop() {
+            if sexp == to {
+                return true;
+            }
+            if visited.insert(sexp) {
+                stack.extend(self.outgoing(sexp).iter().map(|edge| edge.dest));
+            }
+        }
+        false
+    }
+
+    /// Nodes which disappear when the children of all `collapsed` nodes are hidden.
+    ///
+    /// A node stays visible if it can be reached from a root or a global
+    /// without going through a collapsed node.
+    /// Nodes which can't be reached from a collapsed node at all also stay visible,
+    /// even if they're not reachable from anywhere else.
+    #[must_use]
+    pub fn hidden(&self, collapsed: &HashSet<Sexp>) -> HashSet<Sexp> {
+        let mut under_collapsed = HashSet::new();
+        let mut stack: Vec<_> = collapsed
+            .iter()
+            .flat_map(|&sexp| self.outgoing(sexp))
+            .map(|edge| edge.dest)
+            .collect();
+        while let Some(sexp) = stack.pop() {
+            if under_collapsed.insert(sexp) {
+                stack.extend(self.outgoing(sexp).iter().map(|edge| edge.dest));
+            }
+        }
+
+        let mut visible = HashSet::new();
+        let mut stack: Vec<_> = self
+            .sexprecs()
+            .iter()
+            .map(|sexprec| sexprec.address)
+            .filter(|&sexp| {
+                self.is_global(sexp)
+                    || !under_collapsed.contains(&sexp)
+                    || self.roots().iter().any(|root| root.sexp == sexp)
+            })
+            .collect();
+        while let Some(sexp) = stack.pop() {
+            if visible.insert(sexp) && !collapsed.contains(&sexp) {
+                stack.extend(self.outgoing(sexp).iter().map(|edge| edge.dest));
+            }
+        }
+
+        self.sexprecs()
+            .iter()
+            .map(|sexprec| sexprec.address)
+            .filter(|sexp| !visible.contains(sexp))
+            .collect()
+    }
+
+    /// How many of the `hidden` nodes are hidden behind `sexp`,
+    /// i.e. reachable from it only through other hidden nodes.
+    #[must_use]
+    pub fn hidden_under(&self, sexp: Sexp, hidden: &HashSet<Sexp>) -> usize {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<_> = self.outgoing(sexp).iter().map(|edge| edge.dest).collect();
+        while let Some(sexp) = stack.pop() {
+            if hidden.contains(&sexp) && visited.insert(sexp) {
+                stack.extend(self.outgoing(sexp).iter().map(|edge| edge.dest));
+            }
+        }
+        visited.len()
+    }
+
+    /// Nodes reachable from more than one root (see `reachable`),
+    /// mapped to the indices of the roots that reach them.
+    ///
+    /// Globals pointed to directly from a root's subgraph count too
+    /// (e.g. two closures defined in R_GlobalEnv) but they aren't walked through.
+    #[must_use]
+    pub fn shared(&self) -> HashMap<Sexp, Vec<usize>> {
+        let mut reached_by: HashMap<Sexp, Vec<usize>> = HashMap::new();
+        for (i, root) in self.roots().iter().enumerate() {
+            let reachable = self.reachable(root.sexp);
+            let globals: HashSet<_> = reachable
+                .iter()
+                .flat_map(|&sexp| self.outgoing(sexp))
+                .map(|edge| edge.dest)
+                .filter(|&dest| self.is_global(dest))
+                .collect();
+            for sexp in reachable.into_iter().chain(globals) {
+                reached_by.entry(sexp).or_default().push(i);
+            }
+        }
+        reached_by.retain(|_, roots| roots.len() > 1);
+        reached_by
+    }
+}
+
+fn edges_of(sexprec: &Sexprec) -> Vec<Edge> {
+    // The walker doesn't follow any pointers of unknown types, not even attrib.
+    if sexprec.ty.is_unknown() {
+        return Vec::new();
+    }
+
+    let mut edges = vec![Edge {
+        src: sexprec.address,
+        dest: sexprec.attrib,
+        kind: EdgeKind::Attrib,
+        name: "attrib",
+    }];
+    for (i, (name, dest)) in sexprec.payload.pointers().into_iter().enumerate() {
+        edges.push(Edge {
+            src: sexprec.address,
+            dest,
+            kind: EdgeKind::Payload(i),
+            name,
+        });
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn dests(edges: &[Edge]) -> Vec<(&'static str, Sexp)> {
+        edges.iter().map(|edge| (edge.name, edge.dest)).collect()
+    }
+
+    #[test]
+    fn edge_order() {
+        let mut update = pairlist();
+        update.sexprecs[0].attrib = Sexp(0x300);
+        let graph = ObjectGraph::new(update);
+
+        let outgoing = graph.outgoing(Sexp(0x100));
+        assert_eq!(
+            dests(outgoing),
+            [
+                ("attrib", Sexp(0x300)),
+                ("carval", Sexp(0x200)),
+                ("cdrval", Sexp(0x300)),
+                ("tagval", NIL),
+            ]
+        );
+        let kinds: Vec<_> = outgoing.iter().map(|edge| edge.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EdgeKind::Attrib,
+                EdgeKind::Payload(0),
+                EdgeKind::Payload(1),
+                EdgeKind::Payload(2),
+            ]
+        );
+        assert!(outgoing.iter().all(|edge| edge.src == Sexp(0x100)));
+    }
+
+    #[test]
+    fn unknown_types_have_no_edges() {
+        let mut update = pairlist();
+        update.sexprecs[2] = sexprec(0x300, Sexptype::Unknown(42), SexpPayload::Unknown);
+        let graph = ObjectGraph::new(update);
+        assert!(graph.outgoing(Sexp(0x300)).is_empty());
+        assert!(graph.incoming(Sexp(0x400)).is_empty());
+    }
+
+    #[test]
+    fn dangling() {
+        let mut update = pairlist();
+        update.sexprecs[0].attrib = Sexp(0x999);
+        update.sexprecs[2] = list(0x300, Sexp(0x400), Sexp(0x998), NIL);
+        let graph = ObjectGraph::new(update);
+
+        assert_eq!(
+            dests(graph.outgoing(Sexp(0x100))),
+            [
+                ("carval", Sexp(0x200)),
+                ("cdrval", Sexp(0x300)),
+                ("tagval", NIL),
+            ]
+        );
+        assert_eq!(
+            dests(graph.outgoing(Sexp(0x300))),
+            [("attrib", NIL), ("carval", Sexp(0x400)), ("tagval", NIL)]
+        );
+        assert!(!graph.contains(Sexp(0x999)));
+        assert!(graph.incoming(Sexp(0x999)).is_empty());
+        // Not sent at all, not even as a destination.
+        assert!(graph.outgoing(Sexp(0x999)).is_empty());
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut update = pairlist();
+        // Same address, different contents - the second one is ignored.
+        update.sexprecs.push(list(0x100, Sexp(0x400), NIL, NIL));
+        let graph = ObjectGraph::new(update);
+
+        assert_eq!(graph.sexprecs().len(), 6);
+        assert_eq!(graph.get(Sexp(0x100)).unwrap().ty, Sexptype::LISTSXP);
+        assert_eq!(
+            dests(graph.outgoing(Sexp(0x100))),
+            [
+                ("attrib", NIL),
+                ("carval", Sexp(0x200)),
+                ("cdrval", Sexp(0x300)),
+                ("tagval", NIL),
+            ]
+        );
+        // Only 0x300 points to the CHARSXP, the duplicate's edges aren't there.
+        assert_eq!(
+            dests(graph.incoming(Sexp(0x400))),
+            [("carval", Sexp(0x400))]
+        );
+        assert_eq!(graph.incoming(Sexp(0x400))[0].src, Sexp(0x300));
+    }
+
+    #[test]
+    fn incoming() {
+        let graph = ObjectGraph::new(pairlist());
+
+        let srcs = |sexp| -> Vec<_> {
+            graph
+                .incoming(sexp)
+                .iter()
+                .map(|edge| (edge.src, edge.name))
+                .collect()
+        };
+        assert_eq!(srcs(Sexp(0x100)), []);
+        assert_eq!(srcs(Sexp(0x200)), [(Sexp(0x100), "carval")]);
+        assert_eq!(srcs(Sexp(0x300)), [(Sexp(0x100), "cdrval")]);
+        // In the order of the sources in the update, then the order of the fields.
+        assert_eq!(
+            srcs(NIL),
+            [
+                (Sexp(0x100), "attrib"),
+                (Sexp(0x100), "tagval"),
+                (Sexp(0x200), "attrib"),
+                (Sexp(0x300), "attrib"),
+                (Sexp(0x300), "cdrval"),
+                (Sexp(0x300), "tagval"),
+                (Sexp(0x400), "attrib"),
+                (NIL, "attrib"),
+                (NIL, "carval"),
+                (NIL, "cdrval"),
+                (NIL, "tagval"),
+            ]
+        );
+        for sexprec in graph.sexprecs() {
+            for edge in graph.outgoing(sexprec.address) {
+                assert!(graph.incoming(edge.dest).contains(edge));
+            }
+        }
+    }
+
+    /// `pairlist(1, "a")` plus a second root `y` which shares the CHARSXP.
+    fn shared_child() -> Update {
+        let mut update = pairlist();
+        update.roots.push(Root {
+            name: "y".to_owned(),
+            sexp: Sexp(0x500),
+        });
+        update.sexprecs.push(list(0x500, Sexp(0x400), NIL, NIL));
+        update
+    }
+
+    fn set(sexps: &[u64]) -> HashSet<Sexp> {
+        sexps.iter().map(|&sexp| Sexp(sexp)).collect()
+    }
+
+    #[test]
+    fn hidden_shared() {
+        let graph = ObjectGraph::new(shared_child());
+
+        // y can still reach the CHARSXP.
+        let hidden = graph.hidden(&set(&[0x100]));
+        assert_eq!(hidden, set(&[0x200, 0x300]));
+        assert_eq!(graph.hidden_under(Sexp(0x100), &hidden), 2);
+
+        let hidden = graph.hidden(&set(&[0x100, 0x500]));
+        assert_eq!(hidden, set(&[0x200, 0x300, 0x400]));
+        // Badges count everything behind the node, also what's shared with other roots.
+        assert_eq!(graph.hidden_under(Sexp(0x100), &hidden), 3);
+        assert_eq!(graph.hidden_under(Sexp(0x500), &hidden), 1);
+    }
+
+    #[test]
+    fn hidden_reachable_from_global() {
+        let mut update = pairlist();
+        update
+            .sexprecs
+            .push(environment(4, Sexp(0x300), Sexp(5), NIL));
+        let graph = ObjectGraph::new(update);
+
+        let hidden = graph.hidden(&set(&[0x100]));
+        assert_eq!(hidden, set(&[0x200]));
+    }
+
+    #[test]
+    fn hidden_cycle() {
+        // 0x100 -> 0x200 <-> 0x300, 0x300 -> 0x100
+        let update = update(
+            &[("x", 0x100)],
+            vec![
+                list(0x100, Sexp(0x200), NIL, NIL),
+                list(0x200, Sexp(0x300), NIL, NIL),
+                list(0x300, Sexp(0x200), Sexp(0x100), NIL),
+                nil(),
+            ],
+        );
+        let graph = ObjectGraph::new(update);
+
+        let hidden = graph.hidden(&set(&[0x100]));
+        assert_eq!(hidden, set(&[0x200, 0x300]));
+        assert_eq!(graph.hidden_under(Sexp(0x100), &hidden), 2);
+
+        // The root is pointed to from inside the cycle but it's never hidden.
+        let hidden = graph.hidden(&set(&[0x200]));
+        assert_eq!(hidden, set(&[0x300]));
+        assert_eq!(graph.hidden_under(Sexp(0x200), &hidden), 1);
+        assert_eq!(graph.hidden_under(Sexp(0x100), &hidden), 0);
+    }
+
+    #[test]
+    fn hidden_collapsed_root() {
+        let mut update = pairlist();
+        // Not reachable from anything, not under a collapsed node either.
+        update.sexprecs.push(real(0x600, &["1"]));
+        let graph = ObjectGraph::new(update);
+
+        let hidden = graph.hidden(&set(&[0x100]));
+        assert_eq!(hidden, set(&[0x200, 0x300, 0x400]));
+        assert_eq!(graph.hidden_under(Sexp(0x100), &hidden), 3);
+        // Collapsing something already hidden changes nothing.
+        assert_eq!(graph.hidden(&set(&[0x100, 0x300])), hidden);
+        assert!(graph.hidden(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn contracted() {
+        let mut update = pairlist();
+        // A cycle and a second path to the CHARSXP.
+        update.sexprecs[1] = list(0x200, Sexp(0x400), Sexp(0x200), NIL);
+        let graph = ObjectGraph::new(update);
+
+        let contracted = graph.contracted(&set(&[0x100, 0x400]));
+        assert_eq!(contracted.sexprecs().len(), 2);
+        // Named after the first field on the path, only once per destination.
+        let outgoing = contracted.outgoing(Sexp(0x100));
+        assert_eq!(dests(outgoing), [("carval", Sexp(0x400))]);
+        assert_eq!(outgoing[0].kind, EdgeKind::Payload(0));
+        assert_eq!(contracted.incoming(Sexp(0x400)).len(), 1);
+    }
+
+    #[test]
+    fn contracted_through_globals() {
+        let mut update = pairlist();
+        update
+            .sexprecs
+            .push(environment(4, Sexp(0x400), Sexp(5), NIL));
+        update.sexprecs[1] = list(0x200, Sexp(4), NIL, NIL);
+        let graph = ObjectGraph::new(update);
+
+        // The global isn't kept and it isn't walked through either,
+        // otherwise the edge would be carval.
+        let contracted = graph.contracted(&set(&[0x100, 0x400]));
+        assert_eq!(
+            dests(contracted.outgoing(Sexp(0x100))),
+            [("cdrval", Sexp(0x400))]
+        );
+
+        // Kept globals get edges like anything else.
+        let contracted = graph.contracted(&set(&[0x100, 0x400, 4]));
+        assert_eq!(
+            dests(contracted.outgoing(Sexp(0x100))),
+            [("carval", Sexp(4)), ("cdrval", Sexp(0x400))]
+        );
+        assert_eq!(
+            dests(contracted.outgoing(Sexp(4))),
+            [("frame", Sexp(0x400))]
+        );
+    }
+
+    /// `function() 1` and `function() 2`, both defined in R_GlobalEnv.
+    fn two_closures() -> Update {
+        update(
+            &[("f", 0x100), ("g", 0x200)],
+            vec![
+                closure(0x100, NIL, Sexp(0x110), Sexp(4)),
+                real(0x110, &["1"]),
+                closure(0x200, NIL, Sexp(0x210), Sexp(4)),
+                real(0x210, &["2"]),
+                environment(4, Sexp(0x300), Sexp(5), NIL),
+                list(0x300, Sexp(0x100), NIL, NIL),
+                nil(),
+            ],
+        )
+    }
+
+    #[test]
+    fn shared_global() {
+        let graph = ObjectGraph::new(two_closures());
+        let shared = graph.shared();
+        assert_eq!(shared[&Sexp(4)], [0, 1]);
+        // Everything points to R_NilValue.
+        assert_eq!(shared[&NIL], [0, 1]);
+        // The frame of R_GlobalEnv isn't reached through the global.
+        assert_eq!(shared.len(), 2);
+    }
+
+    #[test]
+    fn shared_nodes() {
+        let graph = ObjectGraph::new(shared_child());
+        let shared = graph.shared();
+        assert_eq!(shared[&Sexp(0x400)], [0, 1]);
+        assert!(!shared.contains_key(&Sexp(0x200)));
+        assert!(!shared.contains_key(&Sexp(0x100)));
+
+        // A single root shares nothing, not even globals.
+        assert!(ObjectGraph::new(pairlist()).shared().is_empty());
+    }
+
+    #[test]
+    fn ref_counts() {
+        let mut update = shared_child();
+        // carval of both roots, named 3.
+        update.sexprecs[3].sxpinfo.named = 3;
+        // carval of the first root, named 0.
+        update.sexprecs[1].sxpinfo.named = 0;
+        let graph = ObjectGraph::new(update);
+
+        assert_eq!(graph.ref_count(Sexp(0x300)), Some(RefCount::Matches));
+        assert_eq!(graph.ref_count(Sexp(0x400)), Some(RefCount::Outside(1)));
+        assert_eq!(graph.ref_count(Sexp(0x200)), Some(RefCount::Untracked(1)));
+        // Nothing points to roots, named is 1 from the binding in R.
+        assert_eq!(graph.ref_count(Sexp(0x100)), Some(RefCount::Outside(1)));
+        // Globals are pointed to from all over the heap.
+        assert_eq!(graph.ref_count(NIL), None);
+        assert_eq!(graph.ref_count(Sexp(0x999)), None);
+    }
+
+    #[test]
+    fn ref_count_negative_named() {
+        let mut update = pairlist();
+        update.sexprecs[1].sxpinfo.named = -1;
+        let graph = ObjectGraph::new(update);
+        assert_eq!(graph.ref_count(Sexp(0x200)), Some(RefCount::Untracked(1)));
+    }
+}
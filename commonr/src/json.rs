@@ -0,0 +1,733 @@
+//! JSON representation of `Update` for other tools (Python, jq, ...) - bincode is opaque to them.
+//!
+//! Unlike the bincode format, this is meant to stay stable.
+//! The types here mirror the ones in `data` so that refactoring those doesn't change the JSON,
+//! any change to the JSON needs a new `SCHEMA_VERSION`.
+//!
+//! Addresses and `sxpinfo_bits` are hex strings (`"0x7f0123456789"`) because JSON numbers
+//! can't hold all u64 values (at least not in JavaScript and jq) - the `extra` bits alone
+//! are above 2^53. Types are names like `"CLOSXP"`,
+//! types R shouldn't produce are `"Unknown(<number>)"`.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "pid": 1234,
+//!   "globals": { "R_NilValue": "0x55d0c5e3a2b0", ... },
+//!   "roots": [{ "name": "x", "address": "0x55d0c8f1e5c8" }],
+//!   "sexprecs": [
+//!     {
+//!       "address": "0x55d0c8f1e5c8",
+//!       "type": "REALSXP",
+//!       "type_name": "double",
+//!       "sxpinfo": { "type": 14, "scalar": 1, "obj": 0, "alt": 0, "gp": 0, "mark": 0, "debug": 0,
+//!                    "trace": 0, "spare": 0, "gcgen": 0, "gccls": 1, "named": 1, "extra": 0 },
+//!       "sxpinfo_bits": "0x20000e",
+//!       "attrib": "0x55d0c5e3a2b0",
+//!       "gengc_next_node": "0x...",
+//!       "gengc_prev_node": "0x...",
+//!       "payload": { "kind": "Vector", "length": 1, "truelength": 0, "elements": [], "values": ["1"] }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Payload kinds and their fields (all `0x...` ones are addresses):
+//! - `Vector` - `length`, `truelength`, `elements` (first few, STRSXP, VECSXP and EXPRSXP only),
+//!   `values` (first few, atomic vectors only, formatted like R prints them)
+//! - `String` (CHARSXP) - `length`, `truelength`, `string`
+//! - `Primitive` - `offset`
+//! - `Symbol` - `pname`, `value`, `internal`
+//! - `Cons` (pairlists, calls, dots) - `carval`, `cdrval`, `tagval`
+//! - `Environment` - `frame`, `enclos`, `hashtab`
+//! - `Closure` - `formals`, `body`, `env`
+//! - `Promise` - `value`, `expr`, `env`
+//! - `Unknown` - no fields, inspectr didn't know how to read it
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::data::{
+    Charsxp, Closxp, Envsxp, Globals, Listsxp, Primsxp, Promsxp, Root, Sexp, SexpPayload, Sexprec,
+    Sexptype, Sxpinfo, Symsxp, Update, Vecsxp,
+};
+
+pub const SCHEMA_VERSION: u32 = 1;
+/// Without the dot.
+pub const EXTENSION: &str = "json";
+
+#[derive(Debug)]
+pub enum JsonError {
+    Io(io::Error),
+    /// Invalid JSON or it doesn't match the schema.
+    Format(serde_json::Error),
+    /// The schema version isn't `SCHEMA_VERSION`, we don't know how to read it.
+    UnsupportedVersion(u32),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Io(err) => write!(f, "{}", err),
+            JsonError::Format(err) => write!(f, "invalid JSON update: {}", err),
+            JsonError::UnsupportedVersion(version) => write!(
+                f,
+                "JSON schema version {} is not supported (expected {})",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl Error for JsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JsonError::Io(err) => Some(err),
+            JsonError::Format(err) => Some(err),
+            JsonError::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for JsonError {
+    fn from(err: io::Error) -> Self {
+        JsonError::Io(err)
+    }
+}
+
+/// Pretty-printed so it's readable without jq.
+#[must_use]
+pub fn to_json(update: &Update) -> String {
+    serde_json::to_string_pretty(&JsonUpdate::from(update)).expect("failed to serialize update")
+}
+
+pub fn from_json(json: &str) -> Result<Update, JsonError> {
+    // Check the version first, a newer schema would most likely fail to parse
+    // with a less helpful error.
+    #[derive(Deserialize)]
+    struct Version {
+        schema_version: u32,
+    }
+    let version: Version = serde_json::from_str(json).map_err(JsonError::Format)?;
+    if version.schema_version != SCHEMA_VERSION {
+        return Err(JsonError::UnsupportedVersion(version.schema_version));
+    }
+
+    let update: JsonUpdate = serde_json::from_str(json).map_err(JsonError::Format)?;
+    Ok(update.into())
+}
+
+pub fn write(mut writer: impl Write, update: &Update) -> Result<(), JsonError> {
+    writer.write_all(to_json(update).as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read(mut reader: impl Read) -> Result<Update, JsonError> {
+    let mut json = String::new();
+    reader.read_to_string(&mut json)?;
+    from_json(&json)
+}
+
+pub fn save(path: impl AsRef<Path>, update: &Update) -> Result<(), JsonError> {
+    write(BufWriter::new(File::create(path)?), update)
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Update, JsonError> {
+    read(File::open(path)?)
+}
+
+/// `Sexp` as a hex string.
+#[derive(Debug, Clone, Copy)]
+struct Address(Sexp);
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_hex(self.0 .0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_hex(deserializer, "address").map(|address| Address(Sexp(address)))
+    }
+}
+
+/// The whole sxpinfo as a hex string.
+#[derive(Debug, Clone, Copy)]
+struct SxpinfoBits(u64);
+
+impl Serialize for SxpinfoBits {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_hex(self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SxpinfoBits {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_hex(deserializer, "sxpinfo_bits").map(SxpinfoBits)
+    }
+}
+
+fn serialize_hex<S: Serializer>(value: u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:#x}", value))
+}
+
+/// `what` is for error messages.
+fn deserialize_hex<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    what: &str,
+) -> Result<u64, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    let hex = s
+        .strip_prefix("0x")
+        .ok_or_else(|| de::Error::custom(format!("{} {:?} doesn't start with 0x", what, s)))?;
+    u64::from_str_radix(hex, 16)
+        .map_err(|err| de::Error::custom(format!("invalid {} {:?}: {}", what, s, err)))
+}
+
+/// `Sexptype` as its name.
+#[derive(Debug, Clone, Copy)]
+struct TypeName(Sexptype);
+
+impl Serialize for TypeName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TypeName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if let Some(number) = s
+            .strip_prefix("Unknown(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let number: i32 = number
+                .parse()
+                .map_err(|err| de::Error::custom(format!("invalid type {:?}: {}", s, err)))?;
+            // Each type has only one spelling, known types have to use their name.
+            return match Sexptype::from(number) {
+                ty @ Sexptype::Unknown(_) => Ok(TypeName(ty)),
+                ty => Err(de::Error::custom(format!(
+                    "type {:?} should be {:?}",
+                    s,
+                    ty.to_string()
+                ))),
+            };
+        }
+        (0..=31)
+            .map(Sexptype::from)
+            .find(|ty| !ty.is_unknown() && ty.to_string() == s)
+            .map(TypeName)
+            .ok_or_else(|| de::Error::custom(format!("unknown type {:?}", s)))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonUpdate {
+    schema_version: u32,
+    pid: u32,
+    globals: JsonGlobals,
+    roots: Vec<JsonRoot>,
+    sexprecs: Vec<JsonSexprec>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonGlobals {
+    #[serde(rename = "R_UnboundValue")]
+    unbound_value: Address,
+    #[serde(rename = "R_NilValue")]
+    nil_value: Address,
+    #[serde(rename = "R_MissingArg")]
+    missing_arg: Address,
+    #[serde(rename = "R_GlobalEnv")]
+    global_env: Address,
+    #[serde(rename = "R_EmptyEnv")]
+    empty_env: Address,
+    #[serde(rename = "R_BaseEnv")]
+    base_env: Address,
+    #[serde(rename = "R_BaseNamespace")]
+    base_namespace: Address,
+    #[serde(rename = "R_NamespaceRegistry")]
+    namespace_registry: Address,
+    #[serde(rename = "R_Srcref")]
+    src_ref: Address,
+    #[serde(rename = "R_InBCInterpreter")]
+    in_bc_interpreter: Address,
+    #[serde(rename = "R_CurrentExpression")]
+    current_expression: Address,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonRoot {
+    name: String,
+    address: Address,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonSexprec {
+    address: Address,
+    #[serde(rename = "type")]
+    ty: TypeName,
+    type_name: String,
+    sxpinfo: JsonSxpinfo,
+    sxpinfo_bits: SxpinfoBits,
+    attrib: Address,
+    gengc_next_node: Address,
+    gengc_prev_node: Address,
+    payload: JsonPayload,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonSxpinfo {
+    #[serde(rename = "type")]
+    ty: i32,
+    scalar: i32,
+    obj: i32,
+    alt: i32,
+    gp: i32,
+    mark: i32,
+    debug: i32,
+    trace: i32,
+    spare: i32,
+    gcgen: u32,
+    gccls: u32,
+    named: i32,
+    extra: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind")]
+enum JsonPayload {
+    Vector {
+        length: i64,
+        truelength: i64,
+        elements: Vec<Address>,
+        values: Vec<String>,
+    },
+    String {
+        length: i64,
+        truelength: i64,
+        string: String,
+    },
+    Primitive {
+        offset: i32,
+    },
+    Symbol {
+        pname: Address,
+        value: Address,
+        internal: Address,
+    },
+    Cons {
+        carval: Address,
+        cdrval: Address,
+        tagval: Address,
+    },
+    Environment {
+        frame: Address,
+        enclos: Address,
+        hashtab: Address,
+    },
+    Closure {
+        formals: Address,
+        body: Address,
+        env: Address,
+    },
+    Promise {
+        value: Address,
+        expr: Address,
+        env: Address,
+    },
+    Unknown,
+}
+
+impl From<&Update> for JsonUpdate {
+    fn from(update: &Update) -> Self {
+        let g = &update.globals;
+        Self {
+            schema_version: SCHEMA_VERSION,
+            pid: update.pid,
+            globals: JsonGlobals {
+                unbound_value: Address(g.unbound_value),
+                nil_value: Address(g.nil_value),
+                missing_arg: Address(g.missing_arg),
+                global_env: Address(g.global_env),
+                empty_env: Address(g.empty_env),
+                base_env: Address(g.base_env),
+                base_namespace: Address(g.base_namespace),
+                namespace_registry: Address(g.namespace_registry),
+                src_ref: Address(g.src_ref),
+                in_bc_interpreter: Address(g.in_bc_interpreter),
+                current_expression: Address(g.current_expression),
+            },
+            roots: update
+                .roots
+                .iter()
+                .map(|root| JsonRoot {
+                    name: root.name.clone(),
+                    address: Address(root.sexp),
+                })
+                .collect(),
+            sexprecs: update.sexprecs.iter().map(JsonSexprec::from).collect(),
+        }
+    }
+}
+
+impl From<&Sexprec> for JsonSexprec {
+    fn from(sexprec: &Sexprec) -> Self {
+        let info = &sexprec.sxpinfo;
+        Self {
+            address: Address(sexprec.address),
+            ty: TypeName(sexprec.ty),
+            type_name: sexprec.ty_name.clone(),
+            sxpinfo: JsonSxpinfo {
+                ty: info.ty,
+                scalar: info.scalar,
+                obj: info.obj,
+                alt: info.alt,
+                gp: info.gp,
+                mark: info.mark,
+                debug: info.debug,
+                trace: info.trace,
+                spare: info.spare,
+                gcgen: info.gcgen,
+                gccls: info.gccls,
+                named: info.named,
+                extra: info.extra,
+            },
+            sxpinfo_bits: SxpinfoBits(sexprec.sxpinfo_bits),
+            attrib: Address(sexprec.attrib),
+            gengc_next_node: Address(sexprec.gengc_next_node),
+            gengc_prev_node: Address(sexprec.gengc_prev_node),
+            payload: JsonPayload::from(&sexprec.payload),
+        }
+    }
+}
+
+impl From<&SexpPayload> for JsonPayload {
+    fn from(payload: &SexpPayload) -> Self {
+        match payload {
+            SexpPayload::Vecsxp(v) => JsonPayload::Vector {
+                length: v.length,
+                truelength: v.truelength,
+                elements: v.elements.iter().copied().map(Address).collect(),
+                values: v.values.clone(),
+            },
+            SexpPayload::Charsxp(c) => JsonPayload::String {
+                length: c.length,
+                truelength: c.truelength,
+                string: c.string.clone(),
+            },
+            SexpPayload::Primsxp(p) => JsonPayload::Primitive { offset: p.offset },
+            SexpPayload::Symsxp(s) => JsonPayload::Symbol {
+                pname: Address(s.pname),
+                value: Address(s.value),
+                internal: Address(s.internal),
+            },
+            SexpPayload::Listsxp(l) => JsonPayload::Cons {
+                carval: Address(l.carval),
+                cdrval: Address(l.cdrval),
+                tagval: Address(l.tagval),
+            },
+            SexpPayload::Envsxp(e) => JsonPayload::Environment {
+                frame: Address(e.frame),
+                enclos: Address(e.enclos),
+                hashtab: Address(e.hashtab),
+            },
+            SexpPayload::Closxp(c) => JsonPayload::Closure {
+                formals: Address(c.formals),
+                body: Address(c.body),
+                env: Address(c.env),
+            },
+            SexpPayload::Promsxp(p) => JsonPayload::Promise {
+                value: Address(p.value),
+                expr: Address(p.expr),
+                env: Address(p.env),
+            },
+            SexpPayload::Unknown => JsonPayload::Unknown,
+        }
+    }
+}
+
+impl From<JsonUpdate> for Update {
+    fn from(update: JsonUpdate) -> Self {
+        let g = update.globals;
+        Self {
+            pid: update.pid,
+            globals: Globals {
+                unbound_value: g.unbound_value.0,
+                nil_value: g.nil_value.0,
+                missing_arg: g.missing_arg.0,
+                global_env: g.global_env.0,
+                empty_env: g.empty_env.0,
+                base_env: g.base_env.0,
+                base_namespace: g.base_namespace.0,
+                namespace_registry: g.namespace_registry.0,
+                src_ref: g.src_ref.0,
+                in_bc_interpreter: g.in_bc_interpreter.0,
+                current_expression: g.current_expression.0,
+            },
+            roots: update
+                .roots
+                .into_iter()
+                .map(|root| Root {
+                    name: root.name,
+                    sexp: root.address.0,
+                })
+                .collect(),
+            sexprecs: update.sexprecs.into_iter().map(Sexprec::from).collect(),
+        }
+    }
+}
+
+impl From<JsonSexprec> for Sexprec {
+    fn from(sexprec: JsonSexprec) -> Self {
+        let info = sexprec.sxpinfo;
+        Self {
+            address: sexprec.address.0,
+            ty: sexprec.ty.0,
+            ty_name: sexprec.type_name,
+            sxpinfo: Sxpinfo {
+                ty: info.ty,
+                scalar: info.scalar,
+                obj: info.obj,
+                alt: info.alt,
+                gp: info.gp,
+                mark: info.mark,
+                debug: info.debug,
+                trace: info.trace,
+                spare: info.spare,
+                gcgen: info.gcgen,
+                gccls: info.gccls,
+                named: info.named,
+                extra: info.extra,
+            },
+            sxpinfo_bits: sexprec.sxpinfo_bits.0,
+            attrib: sexprec.attrib.0,
+            gengc_next_node: sexprec.gengc_next_node.0,
+            gengc_prev_node: sexprec.gengc_prev_node.0,
+            payload: sexprec.payload.into(),
+        }
+    }
+}
+
+impl From<JsonPayload> for SexpPayload {
+    fn from(payload: JsonPayload) -> Self {
+        match payload {
+            JsonPayload::Vector {
+                length,
+                truelength,
+                elements,
+                values,
+            } => SexpPayload::Vecsxp(Vecsxp {
+                length,
+                truelength,
+                elements: elements.into_iter().map(|address| address.0).collect(),
+                values,
+            }),
+            JsonPayload::String {
+                length,
+                truelength,
+                string,
+            } => SexpPayload::Charsxp(Charsxp {
+                length,
+                truelength,
+                string,
+            }),
+            JsonPayload::Primitive { offset } => SexpPayload::Primsxp(Primsxp { offset }),
+            JsonPayload::Symbol {
+                pname,
+                value,
+                internal,
+            } => SexpPayload::Symsxp(Symsxp {
+                pname: pname.0,
+                value: value.0,
+                internal: internal.0,
+            }),
+            JsonPayload::Cons {
+                carval,
+                cdrval,
+                tagval,
+            } => SexpPayload::Listsxp(Listsxp {
+                carval: carval.0,
+                cdrval: cdrval.0,
+                tagval: tagval.0,
+            }),
+            JsonPayload::Environment {
+                frame,
+                enclos,
+                hashtab,
+            } => SexpPayload::Envsxp(Envsxp {
+                frame: frame.0,
+                enclos: enclos.0,
+                hashtab: hashtab.0,
+            }),
+            JsonPayload::Closure { formals, body, env } => SexpPayload::Closxp(Closxp {
+                formals: formals.0,
+                body: body.0,
+                env: env.0,
+            }),
+            JsonPayload::Promise { value, expr, env } => SexpPayload::Promsxp(Promsxp {
+                value: value.0,
+                expr: expr.0,
+                env: env.0,
+            }),
+            JsonPayload::Unknown => SexpPayload::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    /// Written by `SCHEMA_VERSION` 1, has to keep parsing as long as the version is supported.
+    const V1: &str = include_str!("../testdata/update-v1.json");
+
+    /// Every payload kind, an unknown type and a few non-default sxpinfo fields.
+    fn every_payload() -> Update {
+        let mut string = charsxp(0x310, "x");
+        // ASCII | CACHED
+        string.sxpinfo.gp = 64 | 32;
+        let mut unknown = sexprec(0xb00, Sexptype::Unknown(42), SexpPayload::Unknown);
+        unknown.sxpinfo.gcgen = 1;
+        unknown.sxpinfo_bits = u64::MAX;
+        let strings = SexpPayload::Vecsxp(Vecsxp {
+            length: 3,
+            truelength: 0,
+            elements: vec![Sexp(0x310), Sexp(0x610)],
+            values: Vec::new(),
+        });
+        let symbol = |address, pname, internal| {
+            let payload = SexpPayload::Symsxp(Symsxp {
+                pname: Sexp(pname),
+                value: Sexp(1),
+                internal,
+            });
+            sexprec(address, Sexptype::SYMSXP, payload)
+        };
+
+        update(
+            &[("f", 0x100), ("p", 0x900), ("u", 0xb00), ("s", 0xc00)],
+            vec![
+                sexprec(
+                    0x100,
+                    Sexptype::CLOSXP,
+                    SexpPayload::Closxp(Closxp {
+                        formals: Sexp(0x200),
+                        body: Sexp(0x500),
+                        env: Sexp(4),
+                    }),
+                ),
+                list(0x200, Sexp(3), NIL, Sexp(0x300)),
+                symbol(0x300, 0x310, NIL),
+                string,
+                list_like(0x500, Sexptype::LANGSXP, Sexp(0x600), Sexp(0x700), NIL),
+                symbol(0x600, 0x610, Sexp(0x800)),
+                charsxp(0x610, "+\"\u{e9}"),
+                sexprec(
+                    0x800,
+                    Sexptype::BUILTINSXP,
+                    SexpPayload::Primsxp(Primsxp { offset: 98 }),
+                ),
+                list(0x700, Sexp(0x300), NIL, NIL),
+                sexprec(
+                    0x900,
+                    Sexptype::PROMSXP,
+                    SexpPayload::Promsxp(Promsxp {
+                        value: Sexp(1),
+                        expr: Sexp(0x300),
+                        env: Sexp(0xa00),
+                    }),
+                ),
+                sexprec(
+                    0xa00,
+                    Sexptype::ENVSXP,
+                    SexpPayload::Envsxp(Envsxp {
+                        frame: NIL,
+                        enclos: Sexp(4),
+                        hashtab: NIL,
+                    }),
+                ),
+                unknown,
+                sexprec(0xc00, Sexptype::STRSXP, strings),
+                real(0xd00, &["1", "NA", "-Inf"]),
+                nil(),
+            ],
+        )
+    }
+
+    fn type_of(json: &str) -> Result<Sexptype, serde_json::Error> {
+        serde_json::from_str::<TypeName>(json).map(|ty| ty.0)
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = to_json(&every_payload());
+        let update = from_json(&json).unwrap();
+        assert_eq!(to_json(&update), json);
+        assert_eq!(update.sexprecs.len(), 15);
+        assert_eq!(update.sexprecs[11].ty, Sexptype::Unknown(42));
+        assert_eq!(update.sexprecs[11].sxpinfo_bits, u64::MAX);
+    }
+
+    #[test]
+    fn golden_v1() {
+        let update = from_json(V1).unwrap();
+        assert_eq!(update.pid, 42);
+        assert_eq!(update.roots[1].name, "p");
+        assert_eq!(update.roots[1].sexp, Sexp(0x900));
+        assert_eq!(update.globals.nil_value, NIL);
+        match &update.sexprecs[6].payload {
+            SexpPayload::Charsxp(charsxp) => assert_eq!(charsxp.string, "+\"\u{e9}"),
+            other => panic!("{:?}", other),
+        }
+        // Writing it again gives the same document, also catches changes to the output.
+        assert_eq!(to_json(&update) + "\n", V1);
+        assert_eq!(to_json(&every_payload()) + "\n", V1);
+    }
+
+    #[test]
+    fn sxpinfo_bits() {
+        let json = V1.replacen(
+            r#""sxpinfo_bits": "0xffffffffffffffff""#,
+            r#""sxpinfo_bits": 18446744073709551615"#,
+            1,
+        );
+        assert!(matches!(from_json(&json), Err(JsonError::Format(_))));
+        let json = V1.replacen(r#""sxpinfo_bits": "0x0""#, r#""sxpinfo_bits": "0""#, 1);
+        assert!(matches!(from_json(&json), Err(JsonError::Format(_))));
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(type_of(r#""CLOSXP""#).unwrap(), Sexptype::CLOSXP);
+        assert_eq!(type_of(r#""Unknown(42)""#).unwrap(), Sexptype::Unknown(42));
+        // Known types only have their name.
+        assert!(type_of(r#""Unknown(3)""#).is_err());
+        // FUNSXP, R's other name for CLOSXP.
+        assert!(type_of(r#""Unknown(99)""#).is_err());
+        assert!(type_of(r#""Unknown(x)""#).is_err());
+        assert!(type_of(r#""closure""#).is_err());
+    }
+
+    #[test]
+    fn bad_version() {
+        let json = V1.replacen(r#""schema_version": 1"#, r#""schema_version": 2"#, 1);
+        assert!(matches!(
+            from_json(&json),
+            Err(JsonError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(from_json("{}"), Err(JsonError::Format(_))));
+    }
+}
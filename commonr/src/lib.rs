@@ -1,2 +1,12 @@
 pub mod data;
+pub mod diff;
+pub mod dot;
+pub mod gp;
+pub mod graph;
+pub mod json;
 pub mod net;
+pub mod semantic;
+pub mod snapshot;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
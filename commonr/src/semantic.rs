@@ -0,0 +1,774 @@
+//! What the objects look like to R code - deparsed calls, binding tables, strings, ...
+//! instead of the SEXPRECs they're made of.
+//!
+//! This is only as good as the update - anything inspectr didn't send
+//! (ALTREP contents, long vectors past the first few elements) is shown as such.
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter, Write},
+};
+
+use crate::{
+    data::{truncate, Sexp, SexpPayload, Sexptype, Vecsxp},
+    graph::ObjectGraph,
+};
+
+/// How deep `deparse` goes before giving up.
+const MAX_DEPTH: usize = 8;
+/// Longer deparsed values get truncated.
+const MAX_LEN: usize = 60;
+
+/// One line of a `Description`, e.g. a binding in an environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// E.g. the binding's name, `[2]` or `attr names`.
+    pub label: String,
+    pub value: String,
+    /// The raw node the value comes from.
+    pub sexp: Sexp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Description {
+    /// E.g. `function(x, y = 2)` or `chr [3]`.
+    pub header: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Display for Description {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header)?;
+        for entry in &self.entries {
+            write!(f, "\n{}: {}", entry.label, entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Nodes which get their own box in the semantic view.
+///
+/// Roots, globals and things with identity (environments, closures, lists, ...).
+/// Everything else (pairlists, calls, symbols, strings, ...) is shown
+/// inside the description of the nearest boxed node.
+#[must_use]
+pub fn boxed(graph: &ObjectGraph) -> HashSet<Sexp> {
+    // Hash tables are VECSXPs but they're part of the environment.
+    let hashtabs: HashSet<_> = graph
+        .sexprecs()
+        .iter()
+        .filter_map(|sexprec| match &sexprec.payload {
+            SexpPayload::Envsxp(envsxp) => Some(envsxp.hashtab),
+            _ => None,
+        })
+        .collect();
+
+    graph
+        .sexprecs()
+        .iter()
+        .map(|sexprec| sexprec.address)
+        .filter(|&sexp| {
+            let ty = graph.get(sexp).unwrap().ty;
+            let boxed_ty = match ty {
+                Sexptype::VECSXP | Sexptype::EXPRSXP => !hashtabs.contains(&sexp),
+                Sexptype::ENVSXP
+                | Sexptype::CLOSXP
+                | Sexptype::S4SXP
+                | Sexptype::EXTPTRSXP
+                | Sexptype::WEAKREFSXP
+                | Sexptype::BCODESXP
+                | Sexptype::Unknown(_) => true,
+                _ => false,
+            };
+            boxed_ty || graph.is_global(sexp) || graph.roots().iter().any(|root| root.sexp == sexp)
+        })
+        .collect()
+}
+
+/// A header and a list of entries, each linking back to the raw node it came from.
+#[must_use]
+pub fn describe(graph: &ObjectGraph, sexp: Sexp) -> Option<Description> {
+    let sexprec = graph.get(sexp)?;
+    let globals = graph.globals();
+    let entry = |label: &str, sexp: Sexp| Entry {
+        label: label.to_owned(),
+        value: deparse(graph, sexp),
+        sexp,
+    };
+
+    let mut entries = Vec::new();
+    let header = match (sexprec.ty, &sexprec.payload) {
+        (Sexptype::NILSXP, _) => "NULL".to_owned(),
+        (Sexptype::SYMSXP, _) => match graph.symbol_name(sexp) {
+            Some(name) => format!("symbol `{}`", name),
+            None => "symbol".to_owned(),
+        },
+        (Sexptype::CHARSXP, SexpPayload::Charsxp(charsxp)) => {
+            quote(&truncate(&charsxp.string, MAX_LEN))
+        }
+        (Sexptype::LANGSXP, _) => {
+            for (i, (tag, value)) in cells(graph, sexp).into_iter().enumerate() {
+                let label = match (i, tag) {
+                    (0, _) => "function".to_owned(),
+                    (_, Some(tag)) => tag.to_owned(),
+                    (_, None) => format!("[{}]", i),
+                };
+                entries.push(entry(&label, value));
+            }
+            format!("call {}", deparse(graph, sexp))
+        }
+        (Sexptype::LISTSXP | Sexptype::DOTSXP, _) => {
+            for (i, (tag, value)) in cells(graph, sexp).into_iter().enumerate() {
+                let label = match tag {
+                    Some(tag) => tag.to_owned(),
+                    None => format!("[{}]", i + 1),
+                };
+                entries.push(entry(&label, value));
+            }
+            if sexprec.ty == Sexptype::DOTSXP {
+                "...".to_owned()
+            } else {
+                "pairlist".to_owned()
+            }
+        }
+        (Sexptype::CLOSXP, SexpPayload::Closxp(closxp)) => {
+            entries.push(entry("body", closxp.body));
+            entries.push(entry("env", closxp.env));
+            let mut header = "function(".to_owned();
+            formals_into(graph, closxp.formals, 0, &mut header);
+            header.push(')');
+            truncate(&header, MAX_LEN)
+        }
+        (Sexptype::ENVSXP, SexpPayload::Envsxp(envsxp)) => {
+            let mut bindings = cells(graph, envsxp.frame);
+            // Hashed environments keep the bindings in pairlists in the buckets instead.
+            if let Some(SexpPayload::Vecsxp(hashtab)) =
+                graph.get(envsxp.hashtab).map(|sexprec| &sexprec.payload)
+            {
+                for &bucket in &hashtab.elements {
+                    bindings.extend(cells(graph, bucket));
+                }
+            }
+            for (tag, value) in bindings {
+                entries.push(entry(tag.unwrap_or("?"), value));
+            }
+            entries.push(entry("enclos", envsxp.enclos));
+            match globals.name(sexp) {
+                Some(name) => format!("environment {}", name),
+                None => "environment".to_owned(),
+            }
+        }
+        (Sexptype::PROMSXP, SexpPayload::Promsxp(promsxp)) => {
+            entries.push(entry("expr", promsxp.expr));
+            if promsxp.value == globals.unbound_value {
+                "promise (not forced)".to_owned()
+            } else {
+                entries.push(entry("value", promsxp.value));
+                "promise (forced)".to_owned()
+            }
+        }
+        (Sexptype::STRSXP | Sexptype::VECSXP | Sexptype::EXPRSXP, SexpPayload::Vecsxp(vecsxp)) => {
+            let names = names(graph, sexp);
+            for (i, &elt) in vecsxp.elements.iter().enumerate() {
+                let label = match names.get(i) {
+                    Some(Some(name)) => name.clone(),
+                    _ if sexprec.ty == Sexptype::STRSXP => format!("[{}]", i + 1),
+                    _ => format!("[[{}]]", i + 1),
+                };
+                entries.push(entry(&label, elt));
+            }
+            vector_header(sexprec.ty, vecsxp)
+        }
+        (_, SexpPayload::Vecsxp(vecsxp)) => {
+            if !vecsxp.values.is_empty() {
+                entries.push(entry("values", sexp));
+            }
+            vector_header(sexprec.ty, vecsxp)
+        }
+        (Sexptype::SPECIALSXP | Sexptype::BUILTINSXP, SexpPayload::Primsxp(primsxp)) => {
+            format!("primitive #{}", primsxp.offset)
+        }
+        _ => sexprec.ty_name.clone(),
+    };
+
+    for (name, value) in cells(graph, sexprec.attrib) {
+        entries.push(entry(&format!("attr {}", name.unwrap_or("?")), value));
+    }
+
+    Some(Description { header, entries })
+}
+
+/// Roughly what R's `deparse` would print, shortened to fit on a line.
+#[must_use]
+pub fn deparse(graph: &ObjectGraph, sexp: Sexp) -> String {
+    let mut out = String::new();
+    deparse_into(graph, sexp, 0, &mut out);
+    truncate(&out, MAX_LEN)
+}
+
+/// Appends to `out`, stops early once `out` is too long to show anyway.
+fn deparse_into(graph: &ObjectGraph, sexp: Sexp, depth: usize, out: &mut String) {
+    if out.chars().count() > MAX_LEN {
+        return;
+    }
+    if depth > MAX_DEPTH {
+        out.push('…');
+        return;
+    }
+
+    let globals = graph.globals();
+    if sexp == globals.missing_arg {
+        return; // Empty argument, e.g. in x[, 1]
+    }
+    if sexp == globals.unbound_value {
+        out.push_str("<unbound>");
+        return;
+    }
+    let sexprec = match graph.get(sexp) {
+        Some(sexprec) => sexprec,
+        None => {
+            match globals.name(sexp) {
+                Some("R_NilValue") => out.push_str("NULL"),
+                Some(name) => write!(out, "<{}>", name).unwrap(),
+                None => out.push_str("<not sent>"),
+            }
+            return;
+        }
+    };
+
+    match (sexprec.ty, &sexprec.payload) {
+        (Sexptype::NILSXP, _) => out.push_str("NULL"),
+        (Sexptype::SYMSXP, _) => out.push_str(graph.symbol_name(sexp).unwrap_or("<symbol>")),
+        (Sexptype::CHARSXP, SexpPayload::Charsxp(charsxp)) => {
+            out.push_str(&quote(&charsxp.string));
+        }
+        (Sexptype::LANGSXP, _) => call_into(graph, sexp, depth, out),
+        (Sexptype::LISTSXP | Sexptype::DOTSXP, _) => {
+            out.push_str("pairlist(");
+            args_into(graph, &cells(graph, sexp), depth, out);
+            out.push(')');
+        }
+        (Sexptype::CLOSXP, SexpPayload::Closxp(closxp)) => {
+            out.push_str("function(");
+            formals_into(graph, closxp.formals, depth, out);
+            out.push_str(") ");
+            deparse_into(graph, closxp.body, depth + 1, out);
+        }
+        (Sexptype::ENVSXP, _) => match globals.name(sexp) {
+            Some(name) => write!(out, "<environment: {}>", name).unwrap(),
+            None => out.push_str("<environment>"),
+        },
+        (Sexptype::PROMSXP, SexpPayload::Promsxp(promsxp)) => {
+            out.push_str("<promise: ");
+            deparse_into(graph, promsxp.expr, depth + 1, out);
+            out.push('>');
+        }
+        (Sexptype::SPECIALSXP | Sexptype::BUILTINSXP, SexpPayload::Primsxp(primsxp)) => {
+            write!(out, "<primitive #{}>", primsxp.offset).unwrap();
+        }
+        (_, SexpPayload::Vecsxp(vecsxp)) => vector_into(graph, sexp, vecsxp, depth, out),
+        _ => write!(out, "<{}>", sexprec.ty_name).unwrap(),
+    }
+}
+
+fn vector_into(graph: &ObjectGraph, sexp: Sexp, vecsxp: &Vecsxp, depth: usize, out: &mut String) {
+    let ty = graph.get(sexp).unwrap().ty;
+    let sent = vecsxp.elements.len().max(vecsxp.values.len());
+    if vecsxp.length > 0 && sent == 0 {
+        // ALTREP
+        write!(out, "<{}>", vector_header(ty, vecsxp)).unwrap();
+        return;
+    }
+
+    let names = names(graph, sexp);
+    let (open, empty) = match ty {
+        Sexptype::VECSXP => ("list(", "list()"),
+        Sexptype::EXPRSXP => ("expression(", "expression()"),
+        Sexptype::LGLSXP => ("c(", "logical(0)"),
+        Sexptype::INTSXP => ("c(", "integer(0)"),
+        Sexptype::REALSXP => ("c(", "numeric(0)"),
+        Sexptype::CPLXSXP => ("c(", "complex(0)"),
+        Sexptype::STRSXP => ("c(", "character(0)"),
+        Sexptype::RAWSXP => ("c(", "raw(0)"),
+        _ => ("c(", "c()"),
+    };
+    if vecsxp.length == 0 {
+        out.push_str(empty);
+        return;
+    }
+    // Scalars are printed without c(), like R does.
+    let scalar = vecsxp.length == 1 && names.is_empty() && open == "c(";
+    if !scalar {
+        out.push_str(open);
+    }
+    for i in 0..sent {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        if let Some(Some(name)) = names.get(i) {
+            write!(out, "{} = ", name).unwrap();
+        }
+        match (vecsxp.elements.get(i), vecsxp.values.get(i)) {
+            (Some(&elt), _) => deparse_into(graph, elt, depth + 1, out),
+            (None, Some(value)) if ty == Sexptype::RAWSXP => {
+                write!(out, "as.raw(0x{})", value).unwrap();
+            }
+            (None, Some(value)) => out.push_str(value),
+            (None, None) => {}
+        }
+        if out.chars().count() > MAX_LEN {
+            return;
+        }
+    }
+    if (sent as i64) < vecsxp.length {
+        out.push_str(", …");
+    }
+    if !scalar {
+        out.push(')');
+    }
+}
+
+/// Binary operators printed between their arguments.
+const INFIX: &[&str] = &[
+    "+", "-", "*", "/", "^", "==", "!=", "<", ">", "<=", ">=", "&", "&&", "|", "||", "<-", "<<-",
+    "=", "~", ":", "$", "@", "|>", "%%", "%/%", "%in%", "%*%", "%o%",
+];
+
+fn call_into(graph: &ObjectGraph, sexp: Sexp, depth: usize, out: &mut String) {
+    let cells = cells(graph, sexp);
+    let (head, args) = match cells.split_first() {
+        Some(((_, head), args)) => (*head, args),
+        None => return,
+    };
+    let untagged = args.iter().all(|(tag, _)| tag.is_none());
+    let arg = |i: usize, out: &mut String| deparse_into(graph, args[i].1, depth + 1, out);
+
+    match graph.symbol_name(head) {
+        Some(op) if INFIX.contains(&op) && args.len() == 2 && untagged => {
+            arg(0, out);
+            // Tight operators like R prints them.
+            if matches!(op, "^" | ":" | "$" | "@") {
+                out.push_str(op);
+            } else {
+                write!(out, " {} ", op).unwrap();
+            }
+            arg(1, out);
+        }
+        Some(op @ ("-" | "+" | "!" | "~")) if args.len() == 1 && untagged => {
+            out.push_str(op);
+            arg(0, out);
+        }
+        Some("(") if args.len() == 1 => {
+            out.push('(');
+            arg(0, out);
+            out.push(')');
+        }
+        Some("{") => {
+            out.push('{');
+            for i in 0..args.len() {
+                out.push_str(if i == 0 { " " } else { "; " });
+                arg(i, out);
+            }
+            out.push_str(" }");
+        }
+        Some(bracket @ ("[" | "[[")) if !args.is_empty() => {
+            arg(0, out);
+            out.push_str(bracket);
+            args_into(graph, &args[1..], depth, out);
+            out.push_str(if bracket == "[" { "]" } else { "]]" });
+        }
+        Some("if") if args.len() == 2 || args.len() == 3 => {
+            out.push_str("if (");
+            arg(0, out);
+            out.push_str(") ");
+            arg(1, out);
+            if args.len() == 3 {
+                out.push_str(" else ");
+                arg(2, out);
+            }
+        }
+        Some("for") if args.len() == 3 => {
+            out.push_str("for (");
+            arg(0, out);
+            out.push_str(" in ");
+            arg(1, out);
+            out.push_str(") ");
+            arg(2, out);
+        }
+        Some("while") if args.len() == 2 => {
+            out.push_str("while (");
+            arg(0, out);
+            out.push_str(") ");
+            arg(1, out);
+        }
+        Some("repeat") if args.len() == 1 => {
+            out.push_str("repeat ");
+            arg(0, out);
+        }
+        Some("function") if args.len() >= 2 => {
+            out.push_str("function(");
+            formals_into(graph, args[0].1, depth, out);
+            out.push_str(") ");
+            arg(1, out);
+        }
+        Some(name) => {
+            write!(out, "{}(", name).unwrap();
+            args_into(graph, args, depth, out);
+            out.push(')');
+        }
+        None => {
+            // E.g. f()() or (function(x) x)(1)
+            deparse_into(graph, head, depth + 1, out);
+            out.push('(');
+            args_into(graph, args, depth, out);
+            out.push(')');
+        }
+    }
+}
+
+fn args_into(graph: &ObjectGraph, args: &[(Option<&str>, Sexp)], depth: usize, out: &mut String) {
+    for (i, (tag, value)) in args.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        if let Some(tag) = tag {
+            write!(out, "{} = ", tag).unwrap();
+        }
+        deparse_into(graph, *value, depth + 1, out);
+    }
+}
+
+/// Formals are a pairlist of default values tagged by the argument names,
+/// arguments without a default have R_MissingArg as the value.
+fn formals_into(graph: &ObjectGraph, formals: Sexp, depth: usize, out: &mut String) {
+    for (i, (tag, value)) in cells(graph, formals).into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        out.push_str(tag.unwrap_or("?"));
+        if value != graph.globals().missing_arg {
+            out.push_str(" = ");
+            deparse_into(graph, value, depth + 1, out);
+        }
+    }
+}
+
+/// The tags and values of a pairlist, call or `...`.
+///
+/// Stops at the end, at a cycle or at a cell which wasn't sent.
+fn cells(graph: &ObjectGraph, mut cell: Sexp) -> Vec<(Option<&str>, Sexp)> {
+    let mut cells = Vec::new();
+    let mut visited = HashSet::new();
+    while visited.insert(cell) {
+        let sexprec = match graph.get(cell) {
+            Some(sexprec) => sexprec,
+            None => break,
+        };
+        let listsxp = match (sexprec.ty, &sexprec.payload) {
+            (
+                Sexptype::LISTSXP | Sexptype::LANGSXP | Sexptype::DOTSXP,
+                SexpPayload::Listsxp(listsxp),
+            ) => listsxp,
+            _ => break,
+        };
+        cells.push((graph.symbol_name(listsxp.tagval), listsxp.carval));
+        cell = listsxp.cdrval;
+    }
+    cells
+}
+
+/// The `names` attribute, empty if there's none.
+fn names(graph: &ObjectGraph, sexp: Sexp) -> Vec<Option<String>> {
+    let names = match graph
+        .attribute(sexp, "names")
+        .and_then(|names| graph.get(names))
+    {
+        Some(names) => names,
+        None => return Vec::new(),
+    };
+    match &names.payload {
+        SexpPayload::Vecsxp(vecsxp) => vecsxp
+            .elements
+            .iter()
+            .map(|&elt| graph.string_value(elt).map(str::to_owned))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A string literal like R prints it - other than Rust's `{:?}`, non-ASCII characters
+/// (e.g. `é`) are kept as they are, only quotes, backslashes and control characters are escaped.
+fn quote(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{7}' => out.push_str("\\a"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{b}' => out.push_str("\\v"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_ascii_control() => write!(out, "\\{:03o}", u32::from(c)).unwrap(),
+            c if c.is_control() => write!(out, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Like `str` prints it, e.g. `num [3]`.
+fn vector_header(ty: Sexptype, vecsxp: &Vecsxp) -> String {
+    let short = match ty {
+        Sexptype::LGLSXP => "logi",
+        Sexptype::INTSXP => "int",
+        Sexptype::REALSXP => "num",
+        Sexptype::CPLXSXP => "cplx",
+        Sexptype::STRSXP => "chr",
+        Sexptype::RAWSXP => "raw",
+        Sexptype::VECSXP => "list",
+        Sexptype::EXPRSXP => "expression",
+        _ => "vector",
+    };
+    let sent = vecsxp.elements.len().max(vecsxp.values.len()) as i64;
+    if sent < vecsxp.length {
+        format!("{} [{}], {} sent", short, vecsxp.length, sent)
+    } else {
+        format!("{} [{}]", short, vecsxp.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::Sexprec, testing::*};
+
+    const MISSING: Sexp = Sexp(3);
+    const GLOBAL_ENV: Sexp = Sexp(4);
+
+    const SYMBOLS: &[&str] = &[
+        "+", "-", "^", "[", "if", "f", "x", "y", "a", "b", "names", "name",
+    ];
+
+    /// A symbol from `SYMBOLS`, its pname is at the next address.
+    fn sym(name: &str) -> Sexp {
+        let i = SYMBOLS.iter().position(|&symbol| symbol == name).unwrap();
+        Sexp(0x1000 + 0x10 * i as u64)
+    }
+
+    /// Cons cells at consecutive addresses starting at `address`.
+    fn cons_cells(address: u64, ty: Sexptype, items: &[(Option<&str>, Sexp)]) -> Vec<Sexprec> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, &(tag, car))| {
+                let address = address + i as u64;
+                let cdr = if i + 1 < items.len() {
+                    Sexp(address + 1)
+                } else {
+                    NIL
+                };
+                list_like(address, ty, car, cdr, tag.map_or(NIL, sym))
+            })
+            .collect()
+    }
+
+    /// An untagged call to `function`.
+    fn call(address: u64, function: &str, args: &[Sexp]) -> Vec<Sexprec> {
+        let mut items = vec![(None, sym(function))];
+        items.extend(args.iter().map(|&arg| (None, arg)));
+        cons_cells(address, Sexptype::LANGSXP, &items)
+    }
+
+    /// `sexprecs` plus the symbols and R_NilValue, the first one is the root.
+    fn graph(mut sexprecs: Vec<Sexprec>) -> ObjectGraph {
+        let root = sexprecs[0].address.0;
+        for (i, name) in SYMBOLS.iter().enumerate() {
+            let address = 0x1000 + 0x10 * i as u64;
+            sexprecs.push(symbol(address, Sexp(address + 1)));
+            sexprecs.push(charsxp(address + 1, name));
+        }
+        sexprecs.push(nil());
+        ObjectGraph::new(update(&[("x", root)], sexprecs))
+    }
+
+    #[test]
+    fn operators() {
+        let mut sexprecs = call(0x100, "+", &[sym("x"), sym("y")]);
+        sexprecs.extend(call(0x110, "^", &[sym("x"), Sexp(0x900)]));
+        sexprecs.extend(call(0x120, "-", &[sym("x")]));
+        sexprecs.extend(call(0x130, "-", &[Sexp(0x100), Sexp(0x120)]));
+        sexprecs.push(real(0x900, &["2"]));
+        let graph = graph(sexprecs);
+
+        assert_eq!(deparse(&graph, Sexp(0x100)), "x + y");
+        assert_eq!(deparse(&graph, Sexp(0x110)), "x^2");
+        assert_eq!(deparse(&graph, Sexp(0x120)), "-x");
+        assert_eq!(deparse(&graph, Sexp(0x130)), "x + y - -x");
+    }
+
+    #[test]
+    fn calls() {
+        let mut sexprecs = call(0x100, "[", &[sym("x"), MISSING, Sexp(0x900)]);
+        sexprecs.extend(call(0x110, "if", &[sym("x"), sym("y"), Sexp(0x120)]));
+        sexprecs.extend(cons_cells(
+            0x120,
+            Sexptype::LANGSXP,
+            &[(None, sym("f")), (Some("a"), Sexp(0x900)), (None, sym("y"))],
+        ));
+        // f(x)(y) - the function is itself a call.
+        sexprecs.extend(cons_cells(
+            0x130,
+            Sexptype::LANGSXP,
+            &[(None, Sexp(0x140)), (None, sym("y"))],
+        ));
+        sexprecs.extend(call(0x140, "f", &[sym("x")]));
+        sexprecs.push(real(0x900, &["1"]));
+        let graph = graph(sexprecs);
+
+        assert_eq!(deparse(&graph, Sexp(0x100)), "x[, 1]");
+        assert_eq!(deparse(&graph, Sexp(0x110)), "if (x) y else f(a = 1, y)");
+        assert_eq!(deparse(&graph, Sexp(0x120)), "f(a = 1, y)");
+        assert_eq!(deparse(&graph, Sexp(0x130)), "f(x)(y)");
+
+        let description = describe(&graph, Sexp(0x120)).unwrap();
+        assert_eq!(description.header, "call f(a = 1, y)");
+        let labels: Vec<_> = description.entries.iter().map(|e| &e.label).collect();
+        assert_eq!(labels, ["function", "a", "[2]"]);
+    }
+
+    #[test]
+    fn formals() {
+        let mut sexprecs = vec![closure(0x100, Sexp(0x200), Sexp(0x300), GLOBAL_ENV)];
+        sexprecs.extend(cons_cells(
+            0x200,
+            Sexptype::LISTSXP,
+            &[(Some("x"), MISSING), (Some("y"), Sexp(0x900))],
+        ));
+        sexprecs.extend(call(0x300, "+", &[sym("x"), sym("y")]));
+        sexprecs.push(real(0x900, &["2"]));
+        let graph = graph(sexprecs);
+
+        assert_eq!(deparse(&graph, Sexp(0x100)), "function(x, y = 2) x + y");
+        let description = describe(&graph, Sexp(0x100)).unwrap();
+        assert_eq!(description.header, "function(x, y = 2)");
+        assert_eq!(
+            description.to_string(),
+            "function(x, y = 2)\nbody: x + y\nenv: <R_GlobalEnv>"
+        );
+        assert_eq!(deparse(&graph, Sexp(0x200)), "pairlist(x = , y = 2)");
+    }
+
+    #[test]
+    fn vectors() {
+        let mut named = real(0x100, &["1", "NA"]);
+        named.attrib = Sexp(0x200);
+        let mut sexprecs = vec![named];
+        sexprecs.extend(cons_cells(
+            0x200,
+            Sexptype::LISTSXP,
+            &[(Some("names"), Sexp(0x300))],
+        ));
+        sexprecs.push(vector(0x300, Sexptype::STRSXP, &[Sexp(0x310), Sexp(0x320)]));
+        sexprecs.push(charsxp(0x310, "a"));
+        sexprecs.push(charsxp(0x320, "b"));
+        sexprecs.push(real(0x400, &["1"]));
+        sexprecs.push(real(0x410, &[]));
+        let mut cut_off = real(0x420, &["1", "2"]);
+        if let SexpPayload::Vecsxp(vecsxp) = &mut cut_off.payload {
+            vecsxp.length = 1000;
+        }
+        sexprecs.push(cut_off);
+        let graph = graph(sexprecs);
+
+        assert_eq!(deparse(&graph, Sexp(0x100)), "c(a = 1, b = NA)");
+        assert_eq!(deparse(&graph, Sexp(0x300)), r#"c("a", "b")"#);
+        assert_eq!(deparse(&graph, Sexp(0x400)), "1");
+        assert_eq!(deparse(&graph, Sexp(0x410)), "numeric(0)");
+        assert_eq!(deparse(&graph, Sexp(0x420)), "c(1, 2, …)");
+        assert_eq!(
+            describe(&graph, Sexp(0x420)).unwrap().header,
+            "num [1000], 2 sent"
+        );
+
+        let description = describe(&graph, Sexp(0x300)).unwrap();
+        assert_eq!(description.header, "chr [2]");
+        assert_eq!(description.entries[1].label, "[2]");
+        assert_eq!(description.entries[1].value, r#""b""#);
+    }
+
+    #[test]
+    fn strings() {
+        let graph = graph(vec![
+            charsxp(0x100, "é \"ü\"\n\\"),
+            charsxp(0x200, "\u{1}\u{7f}\u{85}\t"),
+        ]);
+        assert_eq!(deparse(&graph, Sexp(0x100)), r#""é \"ü\"\n\\""#);
+        assert_eq!(deparse(&graph, Sexp(0x200)), r#""\001\177\u0085\t""#);
+        assert_eq!(
+            describe(&graph, Sexp(0x100)).unwrap().header,
+            r#""é \"ü\"\n\\""#
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let elements: Vec<_> = (0..20).map(|i| Sexp(0x200 + i)).collect();
+        let mut sexprecs = vec![vector(0x100, Sexptype::STRSXP, &elements)];
+        sexprecs.extend(elements.iter().map(|sexp| charsxp(sexp.0, "abcdef")));
+        let graph = graph(sexprecs);
+
+        let deparsed = deparse(&graph, Sexp(0x100));
+        assert_eq!(deparsed.chars().count(), MAX_LEN);
+        assert!(deparsed.starts_with(r#"c("abcdef", "abcdef""#));
+        assert!(deparsed.ends_with('…'));
+    }
+
+    #[test]
+    fn environments() {
+        let mut env = environment(0x100, NIL, GLOBAL_ENV, Sexp(0x200));
+        env.attrib = Sexp(0x500);
+        let mut sexprecs = vec![env];
+        // Two buckets, one of them empty.
+        sexprecs.push(vector(0x200, Sexptype::VECSXP, &[Sexp(0x300), NIL]));
+        sexprecs.extend(cons_cells(
+            0x300,
+            Sexptype::LISTSXP,
+            &[(Some("x"), Sexp(0x900)), (Some("y"), Sexp(0x910))],
+        ));
+        sexprecs.extend(cons_cells(
+            0x500,
+            Sexptype::LISTSXP,
+            &[(Some("name"), Sexp(0x600))],
+        ));
+        sexprecs.push(vector(0x600, Sexptype::STRSXP, &[Sexp(0x610)]));
+        sexprecs.push(charsxp(0x610, "pkg"));
+        sexprecs.push(real(0x900, &["1"]));
+        sexprecs.push(closure(0x910, NIL, sym("x"), Sexp(0x100)));
+        let graph = graph(sexprecs);
+
+        let description = describe(&graph, Sexp(0x100)).unwrap();
+        assert_eq!(
+            description.to_string(),
+            "environment\n\
+             x: 1\n\
+             y: function() x\n\
+             enclos: <R_GlobalEnv>\n\
+             attr name: \"pkg\""
+        );
+        assert_eq!(description.entries[1].sexp, Sexp(0x910));
+        assert_eq!(deparse(&graph, Sexp(0x100)), "<environment>");
+
+        // The hash table is part of the environment, the closure has its own box.
+        let boxed = boxed(&graph);
+        assert!(boxed.contains(&Sexp(0x100)));
+        assert!(!boxed.contains(&Sexp(0x200)));
+        assert!(boxed.contains(&Sexp(0x910)));
+        assert!(!boxed.contains(&Sexp(0x300)));
+        assert!(boxed.contains(&NIL));
+    }
+}
@@ -0,0 +1,226 @@
+//! Snapshot files - an update together with how it was being viewed, saved to disk
+//! so it can be attached to bug reports or used in course material.
+//!
+//! The format is `MAGIC`, the format version as a little endian u32
+//! and then `SnapshotFile` encoded by bincode.
+//! Bincode isn't self-describing so any change to `SnapshotFile`
+//! or to the types in `data` it contains needs a new `VERSION`.
+//! LATER Keep readers for old versions once people have files they care about.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Sexp, Update};
+
+pub const MAGIC: &[u8; 8] = b"RSNAPSHT";
+pub const VERSION: u32 = 1;
+/// Without the dot.
+pub const EXTENSION: &str = "rsnap";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SnapshotFile {
+    pub update: Update,
+    pub view: ViewState,
+}
+
+/// What visualizr needs to show the update the same way again.
+///
+/// inspectr saves the default - visualizr then shows the update as if it just arrived.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ViewState {
+    /// None means fit the graph into the window.
+    pub camera: Option<CameraState>,
+    /// Whether the semantic view was shown instead of the raw one.
+    pub semantic: bool,
+    /// Nodes whose children are hidden. None means the default (globals are collapsed).
+    pub collapsed: Option<HashSet<Sexp>>,
+    /// Top left corners of the boxes in world coordinates.
+    /// Nodes without a position are placed by the layout.
+    pub positions: HashMap<Sexp, [f32; 2]>,
+    /// Notes the user attached to nodes.
+    pub annotations: HashMap<Sexp, String>,
+}
+
+/// Independent of the window size, unlike visualizr's camera offset.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct CameraState {
+    /// The point in world coordinates at the center of the window.
+    pub center: [f32; 2],
+    /// Screen pixels per world unit.
+    pub zoom: f32,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// Doesn't start with `MAGIC`.
+    NotASnapshot,
+    /// The format version isn't `VERSION`, we don't know how to read it.
+    UnsupportedVersion(u32),
+    Corrupted(bincode::Error),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{}", err),
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot file"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot format version {} is not supported (expected {})",
+                version, VERSION
+            ),
+            SnapshotError::Corrupted(err) => write!(f, "corrupted snapshot: {}", err),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            SnapshotError::Corrupted(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+pub fn write(mut writer: impl Write, file: &SnapshotFile) -> Result<(), SnapshotError> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    let buf = bincode::serialize(file).expect("bincode failed to serialize snapshot");
+    writer.write_all(&buf)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read(mut reader: impl Read) -> Result<SnapshotFile, SnapshotError> {
+    let mut magic = [0; MAGIC.len()];
+    match reader.read_exact(&mut magic) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(SnapshotError::NotASnapshot)
+        }
+        Err(err) => return Err(err.into()),
+    }
+    if &magic != MAGIC {
+        return Err(SnapshotError::NotASnapshot);
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+
+    bincode::deserialize_from(reader).map_err(SnapshotError::Corrupted)
+}
+
+pub fn save(path: impl AsRef<Path>, file: &SnapshotFile) -> Result<(), SnapshotError> {
+    write(BufWriter::new(File::create(path)?), file)
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<SnapshotFile, SnapshotError> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, testing::*};
+
+    fn file() -> SnapshotFile {
+        let view = ViewState {
+            camera: Some(CameraState {
+                center: [10.0, -20.5],
+                zoom: 0.25,
+            }),
+            semantic: true,
+            collapsed: Some([Sexp(0x300)].into_iter().collect()),
+            positions: [(Sexp(0x100), [1.0, 2.0])].into_iter().collect(),
+            annotations: [(Sexp(0x200), "a note".to_owned())].into_iter().collect(),
+        };
+        SnapshotFile {
+            update: pairlist(),
+            view,
+        }
+    }
+
+    fn header(version: u32) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend(version.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("commonr-test-{}.rsnap", std::process::id()));
+        save(&path, &file()).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        let expected = file();
+        assert_eq!(
+            json::to_json(&loaded.update),
+            json::to_json(&expected.update)
+        );
+        assert_eq!(loaded.view.camera, expected.view.camera);
+        assert_eq!(loaded.view.semantic, expected.view.semantic);
+        assert_eq!(loaded.view.collapsed, expected.view.collapsed);
+        assert_eq!(loaded.view.positions, expected.view.positions);
+        assert_eq!(loaded.view.annotations, expected.view.annotations);
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut buf = Vec::new();
+        write(&mut buf, &file()).unwrap();
+        buf[0] = b'X';
+        assert!(matches!(read(&*buf), Err(SnapshotError::NotASnapshot)));
+
+        // Too short to even have the magic.
+        assert!(matches!(
+            read(&b"RSN"[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        // JSON exports aren't snapshots either.
+        let json = json::to_json(&pairlist());
+        assert!(matches!(
+            read(json.as_bytes()),
+            Err(SnapshotError::NotASnapshot)
+        ));
+    }
+
+    #[test]
+    fn bad_version() {
+        for version in [0, VERSION + 1, u32::MAX] {
+            match read(&*header(version)) {
+                Err(SnapshotError::UnsupportedVersion(v)) => assert_eq!(v, version),
+                other => panic!("version {}: {:?}", version, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut buf = header(VERSION);
+        buf.extend([0xff; 3]);
+        assert!(matches!(read(&*buf), Err(SnapshotError::Corrupted(_))));
+    }
+}
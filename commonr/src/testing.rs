@@ -0,0 +1,151 @@
+//! Small hand-built updates for tests.
+//!
+//! Globals are at addresses 1 to 11 (R_NilValue is 2), only R_NilValue is sent as a SEXPREC.
+
+use crate::data::*;
+
+pub const NIL: Sexp = Sexp(2);
+
+pub fn globals() -> Globals {
+    Globals {
+        unbound_value: Sexp(1),
+        nil_value: NIL,
+        missing_arg: Sexp(3),
+        global_env: Sexp(4),
+        empty_env: Sexp(5),
+        base_env: Sexp(6),
+        base_namespace: Sexp(7),
+        namespace_registry: Sexp(8),
+        src_ref: Sexp(9),
+        in_bc_interpreter: Sexp(10),
+        current_expression: Sexp(11),
+    }
+}
+
+pub fn sexprec(address: u64, ty: Sexptype, payload: SexpPayload) -> Sexprec {
+    Sexprec {
+        address: Sexp(address),
+        ty,
+        ty_name: format!("{:?}", ty),
+        sxpinfo: Sxpinfo {
+            ty: ty.into(),
+            scalar: 0,
+            obj: 0,
+            alt: 0,
+            gp: 0,
+            mark: 0,
+            debug: 0,
+            trace: 0,
+            spare: 0,
+            gcgen: 0,
+            gccls: 0,
+            named: 1,
+            extra: 0,
+        },
+        sxpinfo_bits: 0,
+        attrib: NIL,
+        gengc_next_node: Sexp(0),
+        gengc_prev_node: Sexp(0),
+        payload,
+    }
+}
+
+/// R_NilValue points to itself, like in memory.c.
+pub fn nil() -> Sexprec {
+    list_like(2, Sexptype::NILSXP, NIL, NIL, NIL)
+}
+
+pub fn list(address: u64, car: Sexp, cdr: Sexp, tag: Sexp) -> Sexprec {
+    list_like(address, Sexptype::LISTSXP, car, cdr, tag)
+}
+
+pub fn list_like(address: u64, ty: Sexptype, car: Sexp, cdr: Sexp, tag: Sexp) -> Sexprec {
+    let payload = SexpPayload::Listsxp(Listsxp {
+        carval: car,
+        cdrval: cdr,
+        tagval: tag,
+    });
+    sexprec(address, ty, payload)
+}
+
+pub fn symbol(address: u64, pname: Sexp) -> Sexprec {
+    let payload = SexpPayload::Symsxp(Symsxp {
+        pname,
+        value: Sexp(1),
+        internal: NIL,
+    });
+    sexprec(address, Sexptype::SYMSXP, payload)
+}
+
+pub fn closure(address: u64, formals: Sexp, body: Sexp, env: Sexp) -> Sexprec {
+    let payload = SexpPayload::Closxp(Closxp { formals, body, env });
+    sexprec(address, Sexptype::CLOSXP, payload)
+}
+
+pub fn environment(address: u64, frame: Sexp, enclos: Sexp, hashtab: Sexp) -> Sexprec {
+    let payload = SexpPayload::Envsxp(Envsxp {
+        frame,
+        enclos,
+        hashtab,
+    });
+    sexprec(address, Sexptype::ENVSXP, payload)
+}
+
+/// A STRSXP, VECSXP or EXPRSXP.
+pub fn vector(address: u64, ty: Sexptype, elements: &[Sexp]) -> Sexprec {
+    let payload = SexpPayload::Vecsxp(Vecsxp {
+        length: elements.len() as i64,
+        truelength: 0,
+        elements: elements.to_vec(),
+        values: Vec::new(),
+    });
+    sexprec(address, ty, payload)
+}
+
+pub fn real(address: u64, values: &[&str]) -> Sexprec {
+    let payload = SexpPayload::Vecsxp(Vecsxp {
+        length: values.len() as i64,
+        truelength: 0,
+        elements: Vec::new(),
+        values: values.iter().map(|&value| value.to_owned()).collect(),
+    });
+    sexprec(address, Sexptype::REALSXP, payload)
+}
+
+pub fn charsxp(address: u64, string: &str) -> Sexprec {
+    let payload = SexpPayload::Charsxp(Charsxp {
+        length: string.len() as i64,
+        truelength: 0,
+        string: string.to_owned(),
+    });
+    sexprec(address, Sexptype::CHARSXP, payload)
+}
+
+pub fn update(roots: &[(&str, u64)], sexprecs: Vec<Sexprec>) -> Update {
+    Update {
+        pid: 42,
+        globals: globals(),
+        roots: roots
+            .iter()
+            .map(|&(name, sexp)| Root {
+                name: name.to_owned(),
+                sexp: Sexp(sexp),
+            })
+            .collect(),
+        sexprecs,
+    }
+}
+
+/// `pairlist(1, "a")` - two cons cells, a double and a CHARSXP.
+pub fn pairlist() -> Update {
+    update(
+        &[("x", 0x100)],
+        vec![
+            list(0x100, Sexp(0x200), Sexp(0x300), NIL),
+            real(0x200, &["1"]),
+            list(0x300, Sexp(0x400), NIL, NIL),
+            charsxp(0x400, "a"),
+            nil(),
+        ],
+    )
+}
@@ -2,10 +2,18 @@
 // Caused by extendr_module!{} but updating doesn't seen to fix it.
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
-use std::{collections::HashSet, ffi::CStr, net::TcpStream};
+use std::{
+    collections::HashSet,
+    ffi::{CStr, CString},
+    net::TcpStream,
+};
 
 use bindingsr::*;
-use commonr::{data::*, net};
+use commonr::{
+    data::*,
+    net,
+    snapshot::{self, SnapshotFile, ViewState},
+};
 use extendr_api::prelude::*;
 
 /*
@@ -42,6 +50,10 @@ For testing:
 30 NEWSXP
 31 FREESXP
 
+multiple roots:
+    x <- c(1, 2) ; visualize(x, y = list(x))
+    f <- function() NULL ; visualize(f, g = f)
+
 large trees:
     11 sexps    visualize(pairlist(1,2,c(3,3,3),4,5))
     64 sexps    visualize(globalenv()) FIXME this breaks visualizr
@@ -54,17 +66,20 @@ large trees:
 //     2) thread from Rust (R is single threaded)
 //     3) pause process?
 
-/// Inspect obj's representation using visualizr.
-/// @export
+/// Send the objects in `env`'s `...` to visualizr, labelled by `labels`.
+/// Use `visualize` instead of calling this directly.
 #[extendr]
-fn visualize(obj: Robj) {
-    let globals = get_globals();
-
-    let sexp = to_sexp(obj);
-
-    let sexprecs = walk_sexps(sexp);
-
-    let update = Update { globals, sexprecs };
+fn visualize_dots(env: Robj, labels: Vec<String>) {
+    let objs = dots_values(to_sexp(env));
+    let update = match make_update(&objs, labels) {
+        Ok(update) => update,
+        Err(err) => {
+            // Rf_error longjmps out without running destructors, free the objects first.
+            drop(objs);
+            throw_r_error(err);
+            return;
+        }
+    };
     rprintln!("{}", update);
     rprintln!("sending {} sexp(s)", update.sexprecs.len());
 
@@ -75,12 +90,105 @@ fn visualize(obj: Robj) {
     net::send(&netmsg, &mut stream).unwrap();
 }
 
-fn walk_sexps(sexp: SEXP) -> Vec<Sexprec> {
+/// Save the `obj` argument of the function whose environment is `env`
+/// to a snapshot file at `path` which visualizr can open later.
+/// Use `visualize_save` instead of calling this directly.
+#[extendr]
+fn save_obj(env: Robj, label: String, path: String) {
+    let obj = arg_value(to_sexp(env), "obj");
+    let update = match make_update(&[obj], vec![label]) {
+        Ok(update) => update,
+        Err(err) => {
+            throw_r_error(err);
+            return;
+        }
+    };
+    let count = update.sexprecs.len();
+    let file = SnapshotFile {
+        update,
+        view: ViewState::default(),
+    };
+    match snapshot::save(&path, &file) {
+        Ok(()) => rprintln!("saved {} sexp(s) to {}", count, path),
+        Err(err) => {
+            // Rf_error longjmps out without running destructors, free the update first.
+            drop(file);
+            throw_r_error(format!("failed to save snapshot to {}: {}", path, err));
+        }
+    }
+}
+
+/// Fails if the number of labels doesn't match the objects.
+/// Panicking here would hang the R session so the callers turn it into an R error.
+fn make_update(objs: &[SEXP], labels: Vec<String>) -> Result<Update, String> {
+    if objs.len() != labels.len() {
+        return Err(format!(
+            "each object needs a label, got {} object(s) and {} label(s)",
+            objs.len(),
+            labels.len()
+        ));
+    }
+
+    let globals = get_globals();
+    let sexprecs = walk_sexps(objs);
+
+    let roots = objs
+        .iter()
+        .zip(labels)
+        .map(|(&sexp, name)| Root {
+            name,
+            sexp: sexp.into(),
+        })
+        .collect();
+    Ok(Update {
+        pid: std::process::id(),
+        globals,
+        roots,
+        sexprecs,
+    })
+}
+
+/// Get the value of the argument `name` of the function whose environment is `env`
+/// without evaluating anything, for the same reason as `dots_values`.
+/// The promise has to be forced on the R side first.
+fn arg_value(env: SEXP, name: &str) -> SEXP {
+    let name = CString::new(name).unwrap();
+    let mut value = unsafe { Rf_findVarInFrame(env, Rf_install(name.as_ptr())) };
+    if Sexptype::from(unsafe { TYPEOF(value) }) == Sexptype::PROMSXP {
+        value = unsafe { PRVALUE(value) };
+    }
+    value
+}
+
+/// Get the values of the `...` arguments of the function whose environment is `env`.
+///
+/// This avoids wrapping them in a list which would add a node
+/// and change their reference counts.
+/// The promises have to be forced on the R side first.
+fn dots_values(env: SEXP) -> Vec<SEXP> {
+    let mut values = Vec::new();
+    let mut cell = unsafe { Rf_findVarInFrame(env, R_DotsSymbol) };
+    // When there are no arguments, `...` is bound to R_MissingArg instead of a DOTSXP.
+    while Sexptype::from(unsafe { TYPEOF(cell) }) == Sexptype::DOTSXP {
+        let mut value = unsafe { CAR(cell) };
+        // Constants can be passed directly, without a promise.
+        if Sexptype::from(unsafe { TYPEOF(value) }) == Sexptype::PROMSXP {
+            value = unsafe { PRVALUE(value) };
+        }
+        values.push(value);
+        cell = unsafe { CDR(cell) };
+    }
+    values
+}
+
+fn walk_sexps(roots: &[SEXP]) -> Vec<Sexprec> {
     let mut walker = Walker {
         visited: HashSet::new(),
         sexprecs: Vec::new(),
     };
-    walker.walk_sexp(sexp);
+    for &root in roots {
+        walker.walk_sexp(root);
+    }
     walker.sexprecs
 }
 
@@ -120,23 +228,26 @@ impl Walker {
         };
         let sxpinfo_bits = sexr.sxpinfo._bitfield_1.get(0, 64);
 
-        let ty = Sexptype::try_from(ty_int).unwrap();
+        // Unknown types (newer R or corrupted memory) are recorded but not walked further
+        // because we don't know which parts of the SEXPREC are pointers.
+        let ty = Sexptype::from(ty_int);
 
         let (payload, ptrs) = match ty {
             Sexptype::SYMSXP => get_symsxp_payload(sexr),
-            Sexptype::LISTSXP | Sexptype::LANGSXP | Sexptype::EXPRSXP => get_listsxp_payload(sexr),
+            Sexptype::LISTSXP | Sexptype::LANGSXP => get_listsxp_payload(sexr),
             Sexptype::CLOSXP  => get_closxp_payload(sexr),
             Sexptype::ENVSXP => get_envsxp_payload(sexr),
             Sexptype::PROMSXP => get_promsxp_payload(sexr),
             Sexptype::SPECIALSXP | Sexptype::BUILTINSXP=>get_primsxp_payload(sexr),
-            Sexptype::CHARSXP
-            | Sexptype::LGLSXP
+            Sexptype::CHARSXP => get_charsxp(sexp),
+            Sexptype::LGLSXP
             | Sexptype::INTSXP
             | Sexptype::REALSXP
             | Sexptype::CPLXSXP
             | Sexptype::STRSXP
             | Sexptype::VECSXP
-            | Sexptype::RAWSXP => get_vecsxp(sexp),
+            | Sexptype::EXPRSXP
+            | Sexptype::RAWSXP => get_vecsxp(sexp, ty, sxpinfo.alt != 0),
             Sexptype::NILSXP // Explicitly initialized as list in memory.c
             | Sexptype::DOTSXP
             | Sexptype::ANYSXP
@@ -146,6 +257,7 @@ impl Walker {
             | Sexptype::S4SXP
             | Sexptype::NEWSXP
             | Sexptype::FREESXP => get_default_payload(sexr),
+            Sexptype::Unknown(_) => (SexpPayload::Unknown, vec![]),
         };
 
         // unsafe {
@@ -160,8 +272,13 @@ impl Walker {
         // }
 
         // LATER Rf_sexptype2char / sexptype2char? (returns the name in CAPS like inspect)
-        let ty_cstr = unsafe { CStr::from_ptr(Rf_type2char(sxpinfo.ty as u32)) };
-        let ty_name = ty_cstr.to_str().unwrap().to_owned();
+        // Rf_type2char warns on unknown types which can turn into an error with options(warn = 2).
+        let ty_name = if ty.is_unknown() {
+            format!("unknown type #{}", ty_int)
+        } else {
+            let ty_cstr = unsafe { CStr::from_ptr(Rf_type2char(sxpinfo.ty as u32)) };
+            ty_cstr.to_str().unwrap().to_owned()
+        };
 
         let sexprec = Sexprec {
             address: sexp.into(),
@@ -175,8 +292,12 @@ impl Walker {
             payload,
         };
 
-        // Visualizr expects the root first so only walk the children after pushing the parent.
+        // Parents come before children, it's easier to read when printed.
         self.sexprecs.push(sexprec);
+        if ty.is_unknown() {
+            // Even attrib could be garbage.
+            return;
+        }
         self.walk_sexp(sexr.attrib);
         for ptr in ptrs {
             self.walk_sexp(ptr);
@@ -212,16 +333,107 @@ fn get_globals() -> Globals {
     }
 }
 
-fn get_vecsxp(sexp: *mut SEXPREC) -> (SexpPayload, Vec<SEXP>) {
+/// How many elements / values of a vector to send.
+/// Big vectors would make the graph unreadable anyway.
+const MAX_ELEMENTS: usize = 100;
+
+fn get_vecsxp(sexp: *mut SEXPREC, ty: Sexptype, alt: bool) -> (SexpPayload, Vec<SEXP>) {
     let sexp_align = sexp as *mut SEXPREC_ALIGN;
     let sexr_align = unsafe { &*sexp_align };
+    let sxp = unsafe { &sexr_align.s.vecsxp };
 
-    // TODO This points to another sexp. Also more sexp pointers after that?
+    // Same as for CHARSXPs, the data starts right after the header.
+    // ALTREP vectors (e.g. 1:10) keep it somewhere else and getting it can allocate.
+    // LATER Use ALTREP's Dataptr_or_null?
+    let count = if alt {
+        0
+    } else {
+        (sxp.length as usize).min(MAX_ELEMENTS)
+    };
+    let data = unsafe { sexp_align.add(1) };
+
+    let mut elements = Vec::new();
+    let mut values = Vec::new();
+    match ty {
+        Sexptype::STRSXP | Sexptype::VECSXP | Sexptype::EXPRSXP => {
+            let ptrs = unsafe { std::slice::from_raw_parts(data as *const SEXP, count) };
+            elements.extend_from_slice(ptrs);
+        }
+        Sexptype::LGLSXP => {
+            let lgls = unsafe { std::slice::from_raw_parts(data as *const i32, count) };
+            values.extend(lgls.iter().map(|&lgl| match lgl {
+                i32::MIN => "NA".to_owned(),
+                0 => "FALSE".to_owned(),
+                _ => "TRUE".to_owned(),
+            }));
+        }
+        Sexptype::INTSXP => {
+            let ints = unsafe { std::slice::from_raw_parts(data as *const i32, count) };
+            values.extend(ints.iter().map(|&int| match int {
+                i32::MIN => "NA".to_owned(),
+                _ => format!("{}L", int),
+            }));
+        }
+        Sexptype::REALSXP => {
+            let reals = unsafe { std::slice::from_raw_parts(data as *const f64, count) };
+            values.extend(reals.iter().map(|&real| format_real(real)));
+        }
+        Sexptype::CPLXSXP => {
+            let cplxs = unsafe { std::slice::from_raw_parts(data as *const Rcomplex, count) };
+            values.extend(
+                cplxs
+                    .iter()
+                    .map(|cplx| format!("{}+{}i", format_real(cplx.r), format_real(cplx.i))),
+            );
+        }
+        Sexptype::RAWSXP => {
+            let raws = unsafe { std::slice::from_raw_parts(data as *const Rbyte, count) };
+            values.extend(raws.iter().map(|raw| format!("{:02x}", raw)));
+        }
+        _ => unreachable!("not a vector type: {}", ty),
+    }
 
-    let sxp = unsafe { &sexr_align.s.vecsxp };
     let payload = SexpPayload::Vecsxp(Vecsxp {
         length: sxp.length as i64,
         truelength: sxp.truelength as i64,
+        elements: elements.iter().map(|&elt| elt.into()).collect(),
+        values,
+    });
+    (payload, elements)
+}
+
+fn format_real(real: f64) -> String {
+    if unsafe { R_IsNA(real) } != 0 {
+        "NA".to_owned()
+    } else if real.is_nan() {
+        "NaN".to_owned()
+    } else if real.is_infinite() {
+        if real > 0.0 { "Inf" } else { "-Inf" }.to_owned()
+    } else {
+        real.to_string()
+    }
+}
+
+/// How many bytes of a string to send, `Charsxp::length` is still the full length.
+const MAX_STRING: usize = 256;
+
+fn get_charsxp(sexp: *mut SEXPREC) -> (SexpPayload, Vec<SEXP>) {
+    let sexp_align = sexp as *mut SEXPREC_ALIGN;
+    let sexr_align = unsafe { &*sexp_align };
+    let sxp = unsafe { &sexr_align.s.vecsxp };
+
+    // The data starts right after the header, that's what STDVEC_DATAPTR does.
+    // Not using CHAR() because it's not exported and translateChar can allocate or error.
+    // LATER Could a CHARSXP be ALTREP? Then this would read garbage.
+    let data = unsafe { sexp_align.add(1) as *const u8 };
+    let count = (sxp.length as usize).min(MAX_STRING);
+    let bytes = unsafe { std::slice::from_raw_parts(data, count) };
+    let string = String::from_utf8_lossy(bytes).into_owned();
+
+    let payload = SexpPayload::Charsxp(Charsxp {
+        length: sxp.length as i64,
+        truelength: sxp.truelength as i64,
+        string,
     });
     (payload, vec![])
 }
@@ -303,5 +515,6 @@ fn get_default_payload(sexr: &SEXPREC) -> (SexpPayload, Vec<SEXP>) {
 // See corresponding C code in `entrypoint.c`.
 extendr_module! {
     mod inspectr;
-    fn visualize;
+    fn visualize_dots;
+    fn save_obj;
 }
@@ -0,0 +1,35 @@
+//! Command line parsing helpers shared by the renderr and visualizr binaries.
+//!
+//! Not worth a dependency for a couple of options.
+
+use std::{fmt::Display, process, str::FromStr};
+
+/// Unwrap the result of parsing the command line, print usage and exit on errors.
+///
+/// `None` means help was requested.
+#[must_use]
+pub fn or_exit<T>(parsed: Result<Option<T>, String>, usage: &str) -> T {
+    match parsed {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            println!("{}", usage);
+            process::exit(0);
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, usage);
+            process::exit(2);
+        }
+    }
+}
+
+/// Parse the value following `option`.
+pub fn value<T>(option: &str, value: Option<String>) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = value.ok_or_else(|| format!("{} needs a value", option))?;
+    value
+        .parse()
+        .map_err(|err| format!("invalid value for {}: {:?} ({})", option, value, err))
+}
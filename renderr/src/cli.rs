@@ -0,0 +1,248 @@
+use std::path::PathBuf;
+
+use commonr::dot::DotOptions;
+use renderr::{
+    args::{self, value},
+    colors::ColorScheme,
+};
+
+const USAGE: &str = "\
+usage: renderr dot [dot options] <file>
+       renderr svg [svg options] <file>
+
+Exports snapshot files (saved by visualize_save in R or Ctrl+S in visualizr)
+and JSON files (.json, exported by Ctrl+E) without opening a window.
+
+dot - export as a Graphviz graph:
+    -o, --output <path>    where to write it (default stdout)
+    --depth <n>            only nodes at most n pointers away from a root
+    --sxpinfo              show named and GP flags
+    --no-values            don't show vector values, strings and symbol names
+    --no-attrib            leave out attributes
+    --no-globals           leave out pointers to globals like R_NilValue
+    --skip <field>         leave out pointers stored in this field (e.g. enclos), repeatable
+
+svg - render the way visualizr shows it:
+    -o, --output <path>    where to write it (default stdout)
+    --zoom <z>             decides the size and level of detail (default the saved zoom or 1)
+    --colors <scheme>      type, generation, named or object (default type)";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Dot(DotArgs),
+    Svg(SvgArgs),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotArgs {
+    pub file: PathBuf,
+    /// None means stdout.
+    pub output: Option<PathBuf>,
+    pub options: DotOptions,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgArgs {
+    pub file: PathBuf,
+    /// None means stdout.
+    pub output: Option<PathBuf>,
+    /// None means the zoom saved in the file.
+    pub zoom: Option<f32>,
+    pub scheme: ColorScheme,
+}
+
+impl Command {
+    /// Parse the command line, print usage and exit on errors.
+    #[must_use]
+    pub fn parse() -> Self {
+        args::or_exit(Self::parse_from(std::env::args().skip(1)), USAGE)
+    }
+
+    /// Returns None if help was requested.
+    fn parse_from(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        match args.next().as_deref() {
+            Some("dot") => Ok(parse_dot(args)?.map(Command::Dot)),
+            Some("svg") => Ok(parse_svg(args)?.map(Command::Svg)),
+            Some("-h" | "--help") => Ok(None),
+            Some(command) => Err(format!("unknown command: {}", command)),
+            None => Err("missing command".to_owned()),
+        }
+    }
+}
+
+fn parse_dot(mut args: impl Iterator<Item = String>) -> Result<Option<DotArgs>, String> {
+    let mut file = None;
+    let mut output = None;
+    let mut options = DotOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value(&arg, args.next())?),
+            "--depth" => options.max_depth = Some(value(&arg, args.next())?),
+            "--sxpinfo" => options.sxpinfo = true,
+            "--no-values" => options.values = false,
+            "--no-attrib" => options.attrib = false,
+            "--no-globals" => options.globals = false,
+            "--skip" => {
+                options.skip.insert(value(&arg, args.next())?);
+            }
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+            _ if file.is_some() => return Err("dot takes exactly one file".to_owned()),
+            _ => file = Some(arg.into()),
+        }
+    }
+    let file = file.ok_or_else(|| "dot needs a file to export".to_owned())?;
+    Ok(Some(DotArgs {
+        file,
+        output,
+        options,
+    }))
+}
+
+fn parse_svg(mut args: impl Iterator<Item = String>) -> Result<Option<SvgArgs>, String> {
+    let mut file = None;
+    let mut output = None;
+    let mut zoom = None;
+    let mut scheme = ColorScheme::Type;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value(&arg, args.next())?),
+            "--zoom" => {
+                let z: f32 = value(&arg, args.next())?;
+                if !(z > 0.0 && z.is_finite()) {
+                    return Err("--zoom must be positive".to_owned());
+                }
+                zoom = Some(z);
+            }
+            "--colors" => scheme = value(&arg, args.next())?,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+            _ if file.is_some() => return Err("svg takes exactly one file".to_owned()),
+            _ => file = Some(arg.into()),
+        }
+    }
+    let file = file.ok_or_else(|| "svg needs a file to render".to_owned())?;
+    Ok(Some(SvgArgs {
+        file,
+        output,
+        zoom,
+        scheme,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Command>, String> {
+        Command::parse_from(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn dot() {
+        let parsed = parse(&[
+            "dot",
+            "--depth",
+            "3",
+            "--sxpinfo",
+            "--no-values",
+            "--no-attrib",
+            "--no-globals",
+            "--skip",
+            "enclos",
+            "--skip",
+            "attrib",
+            "-o",
+            "out.dot",
+            "in.json",
+        ]);
+        let mut options = DotOptions {
+            max_depth: Some(3),
+            sxpinfo: true,
+            values: false,
+            attrib: false,
+            globals: false,
+            ..DotOptions::default()
+        };
+        options.skip.insert("enclos".to_owned());
+        options.skip.insert("attrib".to_owned());
+        let expected = Command::Dot(DotArgs {
+            file: "in.json".into(),
+            output: Some("out.dot".into()),
+            options,
+        });
+        assert_eq!(parsed, Ok(Some(expected)));
+
+        let expected = Command::Dot(DotArgs {
+            file: "in.json".into(),
+            output: None,
+            options: DotOptions::default(),
+        });
+        assert_eq!(parse(&["dot", "in.json"]), Ok(Some(expected)));
+    }
+
+    #[test]
+    fn svg() {
+        let parsed = parse(&[
+            "svg", "in.snap", "--zoom", "0.5", "--colors", "named", "--output", "out.svg",
+        ]);
+        let expected = Command::Svg(SvgArgs {
+            file: "in.snap".into(),
+            output: Some("out.svg".into()),
+            zoom: Some(0.5),
+            scheme: ColorScheme::Named,
+        });
+        assert_eq!(parsed, Ok(Some(expected)));
+
+        let expected = Command::Svg(SvgArgs {
+            file: "in.snap".into(),
+            output: None,
+            zoom: None,
+            scheme: ColorScheme::Type,
+        });
+        assert_eq!(parse(&["svg", "in.snap"]), Ok(Some(expected)));
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert_eq!(parse(&["dot", "-h"]), Ok(None));
+        assert_eq!(parse(&["svg", "in.snap", "--help"]), Ok(None));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |args: &[&str]| parse(args).unwrap_err();
+        assert_eq!(err(&[]), "missing command");
+        assert_eq!(err(&["png", "in.snap"]), "unknown command: png");
+        assert_eq!(
+            err(&["dot", "--bogus", "in.snap"]),
+            "unknown argument: --bogus"
+        );
+        assert_eq!(err(&["svg", "-x"]), "unknown argument: -x");
+        assert_eq!(err(&["dot"]), "dot needs a file to export");
+        assert_eq!(
+            err(&["svg", "a.snap", "b.snap"]),
+            "svg takes exactly one file"
+        );
+        assert_eq!(err(&["dot", "in.snap", "-o"]), "-o needs a value");
+        assert_eq!(err(&["dot", "in.snap", "--skip"]), "--skip needs a value");
+        assert_eq!(err(&["svg", "in.snap", "--zoom"]), "--zoom needs a value");
+        assert_eq!(
+            err(&["svg", "in.snap", "--zoom", "0"]),
+            "--zoom must be positive"
+        );
+        assert_eq!(
+            err(&["svg", "in.snap", "--zoom", "inf"]),
+            "--zoom must be positive"
+        );
+        assert_eq!(
+            err(&["dot", "in.snap", "--depth", "-1"]),
+            r#"invalid value for --depth: "-1" (invalid digit found in string)"#
+        );
+        assert_eq!(
+            err(&["svg", "in.snap", "--colors", "pink"]),
+            r#"invalid value for --colors: "pink" (expected type, generation, named or object)"#
+        );
+    }
+}
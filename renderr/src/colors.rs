@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+use commonr::{
+    data::{Sexprec, Sexptype},
+    diff::Status,
+    graph::ObjectGraph,
+};
+
+use crate::geometry::Color;
+
+/// What node colors mean.
+///
+/// Each scheme sorts nodes into a few categories,
+/// the legend lists them in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    Type,
+    /// gcgen and mark
+    Generation,
+    /// Reference counting - anything above 1 gets copied on modification.
+    Named,
+    /// The obj bit and the class attribute, they should agree.
+    Object,
+}
+
+#[rustfmt::skip]
+const TYPE_LEGEND: &[(&str, Color)] = &[
+    ("NULL",                        Color::new(0.75, 0.75, 0.75, 1.0)),
+    ("symbol",                      Color::new(0.75, 0.60, 0.90, 1.0)),
+    ("pairlist / call / dots",      Color::new(0.55, 0.70, 0.95, 1.0)),
+    ("closure",                     Color::new(1.00, 0.65, 0.30, 1.0)),
+    ("environment",                 Color::new(0.45, 0.80, 0.45, 1.0)),
+    ("promise",                     Color::new(0.95, 0.90, 0.35, 1.0)),
+    ("special / builtin",           Color::new(0.75, 0.55, 0.40, 1.0)),
+    ("CHARSXP",                     Color::new(0.95, 0.65, 0.80, 1.0)),
+    ("logical / number / raw",      Color::new(0.35, 0.80, 0.80, 1.0)),
+    ("character",                   Color::new(0.90, 0.45, 0.70, 1.0)),
+    ("list / expression",           Color::new(0.40, 0.55, 0.85, 1.0)),
+    ("other",                       Color::new(0.90, 0.90, 0.90, 1.0)),
+    ("unknown",                     Color::new(1.00, 0.30, 0.30, 1.0)),
+];
+
+#[rustfmt::skip]
+const GENERATION_LEGEND: &[(&str, Color)] = &[
+    ("gen 0, unmarked",             Color::new(0.70, 0.90, 1.00, 1.0)),
+    ("gen 0, marked",               Color::new(0.30, 0.60, 0.95, 1.0)),
+    ("gen 1, unmarked",             Color::new(1.00, 0.85, 0.65, 1.0)),
+    ("gen 1, marked",               Color::new(0.95, 0.55, 0.20, 1.0)),
+];
+
+#[rustfmt::skip]
+const NAMED_LEGEND: &[(&str, Color)] = &[
+    ("named 0",                     Color::new(0.80, 0.95, 0.80, 1.0)),
+    ("named 1",                     Color::new(1.00, 0.95, 0.60, 1.0)),
+    ("named > 1",                   Color::new(1.00, 0.45, 0.30, 1.0)),
+];
+
+#[rustfmt::skip]
+const OBJECT_LEGEND: &[(&str, Color)] = &[
+    ("not an object",               Color::new(0.85, 0.85, 0.85, 1.0)),
+    ("obj bit + class attribute",   Color::new(0.60, 0.45, 0.95, 1.0)),
+    ("obj bit without class",       Color::new(1.00, 0.30, 0.30, 1.0)),
+    ("class without obj bit",       Color::new(1.00, 0.65, 0.20, 1.0)),
+];
+
+#[rustfmt::skip]
+pub const DIFF_LEGEND: &[(&str, Color)] = &[
+    ("same",                        Color::new(0.85, 0.85, 0.85, 1.0)),
+    ("changed",                     Color::new(1.00, 0.80, 0.30, 1.0)),
+    ("added",                       Color::new(0.45, 0.85, 0.45, 1.0)),
+    ("removed",                     Color::new(1.00, 0.45, 0.45, 1.0)),
+];
+
+/// Colors for comparing snapshots, they replace the current scheme.
+#[must_use]
+pub fn diff_color(status: &Status) -> Color {
+    let category = match status {
+        Status::Same => 0,
+        Status::Changed(_) => 1,
+        Status::Added => 2,
+        Status::Removed => 3,
+    };
+    DIFF_LEGEND[category].1
+}
+
+impl ColorScheme {
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            ColorScheme::Type => ColorScheme::Generation,
+            ColorScheme::Generation => ColorScheme::Named,
+            ColorScheme::Named => ColorScheme::Object,
+            ColorScheme::Object => ColorScheme::Type,
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Type => "type",
+            ColorScheme::Generation => "GC generation",
+            ColorScheme::Named => "named",
+            ColorScheme::Object => "object",
+        }
+    }
+
+    #[must_use]
+    pub fn legend(self) -> &'static [(&'static str, Color)] {
+        match self {
+            ColorScheme::Type => TYPE_LEGEND,
+            ColorScheme::Generation => GENERATION_LEGEND,
+            ColorScheme::Named => NAMED_LEGEND,
+            ColorScheme::Object => OBJECT_LEGEND,
+        }
+    }
+
+    #[must_use]
+    pub fn color(self, graph: &ObjectGraph, sexprec: &Sexprec) -> Color {
+        self.legend()[self.category(graph, sexprec)].1
+    }
+
+    /// Index into the legend.
+    fn category(self, graph: &ObjectGraph, sexprec: &Sexprec) -> usize {
+        match self {
+            ColorScheme::Type => match sexprec.ty {
+                Sexptype::NILSXP => 0,
+                Sexptype::SYMSXP => 1,
+                Sexptype::LISTSXP | Sexptype::LANGSXP | Sexptype::DOTSXP => 2,
+                Sexptype::CLOSXP => 3,
+                Sexptype::ENVSXP => 4,
+                Sexptype::PROMSXP => 5,
+                Sexptype::SPECIALSXP | Sexptype::BUILTINSXP => 6,
+                Sexptype::CHARSXP => 7,
+                Sexptype::LGLSXP
+                | Sexptype::INTSXP
+                | Sexptype::REALSXP
+                | Sexptype::CPLXSXP
+                | Sexptype::RAWSXP => 8,
+                Sexptype::STRSXP => 9,
+                Sexptype::VECSXP | Sexptype::EXPRSXP => 10,
+                Sexptype::ANYSXP
+                | Sexptype::BCODESXP
+                | Sexptype::EXTPTRSXP
+                | Sexptype::WEAKREFSXP
+                | Sexptype::S4SXP
+                | Sexptype::NEWSXP
+                | Sexptype::FREESXP => 11,
+                Sexptype::Unknown(_) => 12,
+            },
+            ColorScheme::Generation => {
+                let old = sexprec.sxpinfo.gcgen != 0;
+                let marked = sexprec.sxpinfo.mark != 0;
+                usize::from(old) * 2 + usize::from(marked)
+            }
+            ColorScheme::Named => match sexprec.sxpinfo.named {
+                i32::MIN..=0 => 0,
+                1 => 1,
+                _ => 2,
+            },
+            ColorScheme::Object => {
+                let obj = sexprec.sxpinfo.obj != 0;
+                let class = graph.attribute(sexprec.address, "class").is_some();
+                match (obj, class) {
+                    (false, false) => 0,
+                    (true, true) => 1,
+                    (true, false) => 2,
+                    (false, true) => 3,
+                }
+            }
+        }
+    }
+}
+
+/// The names used on the command line.
+impl FromStr for ColorScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "type" => Ok(ColorScheme::Type),
+            "generation" => Ok(ColorScheme::Generation),
+            "named" => Ok(ColorScheme::Named),
+            "object" => Ok(ColorScheme::Object),
+            _ => Err("expected type, generation, named or object".to_owned()),
+        }
+    }
+}
+
+/// A paler version of the color for backgrounds with text on them.
+#[must_use]
+pub fn pale(color: Color) -> Color {
+    let mix = |c: f32| c + (1.0 - c) * 0.6;
+    Color::new(mix(color.r), mix(color.g), mix(color.b), color.a)
+}
+
+/// In screen pixels, the legend doesn't zoom.
+pub const LEGEND_FONT_SIZE: f32 = 24.0;
+pub const LEGEND_SWATCH: f32 = 18.0;
+pub const LEGEND_PADDING: f32 = 10.0;
+
+/// The title and a line for each category.
+#[must_use]
+pub fn legend_height(legend: &[(&str, Color)]) -> f32 {
+    (legend.len() + 1) as f32 * LEGEND_FONT_SIZE + 2.0 * LEGEND_PADDING
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+
+use commonr::{
+    data::Sexp,
+    graph::{Edge, EdgeKind, ObjectGraph},
+};
+
+use crate::{
+    geometry::{vec2, Color, Rect, Vec2, BLUE, DARKBLUE, DARKGREEN, GRAY},
+    layout::{edge_offsets, is_cons_row, BOX_GAP, BOX_HEIGHT, BOX_WIDTH},
+};
+
+/// How far edges go straight out of / into a box before turning.
+const STUB: f32 = BOX_GAP / 2.0;
+/// Length of edges into globals - those aren't routed, only hinted at.
+const GLOBAL_STUB: f32 = 40.0;
+pub const ARROW_SIZE: f32 = 14.0;
+/// Dashed edges, in screen pixels.
+pub const DASH: f32 = 12.0;
+pub const DASH_GAP: f32 = 8.0;
+/// Cells of the grid used to find boxes near a segment.
+const GRID_CELL: f32 = BOX_WIDTH;
+/// Rough width of a character in edge labels, only used for placement.
+pub const LABEL_CHAR_WIDTH: f32 = 9.0;
+pub const LABEL_FONT_SIZE: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeStyle {
+    Attrib,
+    Payload,
+    /// cdrval between cons cells
+    Row,
+    /// Edge into a global, drawn as a short stub.
+    Global,
+}
+
+impl EdgeStyle {
+    pub fn color(self) -> Color {
+        match self {
+            EdgeStyle::Attrib => DARKGREEN,
+            EdgeStyle::Payload => BLUE,
+            EdgeStyle::Row => DARKBLUE,
+            EdgeStyle::Global => GRAY,
+        }
+    }
+
+    pub fn thickness(self) -> f32 {
+        match self {
+            EdgeStyle::Row => 3.0,
+            _ => 2.0,
+        }
+    }
+
+    pub fn dashed(self) -> bool {
+        matches!(self, EdgeStyle::Attrib | EdgeStyle::Global)
+    }
+}
+
+/// An edge converted into something drawable, in world coordinates.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub style: EdgeStyle,
+    /// Polyline of axis-aligned segments.
+    pub points: Vec<Vec2>,
+    /// Direction of the last segment, None means no arrowhead.
+    pub arrow: Option<Vec2>,
+    pub label: String,
+    /// Bottom left of the label text.
+    pub label_pos: Vec2,
+}
+
+/// Route all edges between nodes which have a position.
+///
+/// Routes are orthogonal and try to avoid crossing boxes.
+/// We only try a handful of candidate routes through the gaps around the source and destination
+/// and pick the one crossing the fewest boxes - proper routing around obstacles
+/// is a lot of work and this is good enough for tree-like layouts.
+pub fn route_edges(graph: &ObjectGraph, positions: &HashMap<Sexp, Vec2>) -> Vec<Route> {
+    let obstacles = Obstacles::new(positions);
+    let mut routes = Vec::new();
+    for sexprec in graph.sexprecs() {
+        for edge in graph.outgoing(sexprec.address) {
+            // Nodes without a position are hidden.
+            if positions.contains_key(&edge.src) && positions.contains_key(&edge.dest) {
+                routes.push(route_edge(graph, positions, &obstacles, edge));
+            }
+        }
+    }
+    routes
+}
+
+fn route_edge(
+    graph: &ObjectGraph,
+    positions: &HashMap<Sexp, Vec2>,
+    obstacles: &Obstacles,
+    edge: &Edge,
+) -> Route {
+    let (src_offset, dest_offset) = edge_offsets(graph, edge);
+    let src = positions[&edge.src] + src_offset;
+    let out_dir = exit_direction(graph, edge);
+    // Symbols and strings are named after their contents so the edge alone says what's there.
+    let label = match graph.short_name(edge.dest) {
+        Some(name) => format!("{} → {}", edge.name, name),
+        None => edge.name.to_owned(),
+    };
+    let label_pos = label_position(src, out_dir, &label);
+
+    if graph.is_global(edge.dest) {
+        let end = src + out_dir * GLOBAL_STUB;
+        let name = graph.globals().name(edge.dest).unwrap();
+        let name_pos = label_position(end, out_dir, name);
+        return Route {
+            style: EdgeStyle::Global,
+            points: vec![src, end],
+            arrow: None,
+            label: format!("{} → {}", edge.name, name),
+            label_pos: if out_dir.y > 0.0 { name_pos } else { label_pos },
+        };
+    }
+
+    let style = match edge.kind {
+        EdgeKind::Attrib => EdgeStyle::Attrib,
+        EdgeKind::Payload(_) if is_cons_row(graph, edge) => EdgeStyle::Row,
+        EdgeKind::Payload(_) => EdgeStyle::Payload,
+    };
+
+    let (dest, in_dir) = if style == EdgeStyle::Row {
+        // Into the left side
+        (positions[&edge.dest] + dest_offset, vec2(1.0, 0.0))
+    } else {
+        // Into the top
+        let dest = positions[&edge.dest] + dest_offset + entry_nudge(edge);
+        (dest, vec2(0.0, 1.0))
+    };
+
+    let src_out = src + out_dir * STUB;
+    let dest_in = dest - in_dir * STUB;
+
+    let src_box = positions[&edge.src];
+    let dest_box = positions[&edge.dest];
+    let xs = [
+        src_out.x,
+        dest_in.x,
+        src_box.x - BOX_GAP / 2.0,
+        src_box.x + BOX_WIDTH + BOX_GAP / 2.0,
+        dest_box.x - BOX_GAP / 2.0,
+        dest_box.x + BOX_WIDTH + BOX_GAP / 2.0,
+    ];
+    let ys = [
+        src_out.y,
+        dest_in.y,
+        src_box.y + BOX_HEIGHT + BOX_GAP / 2.0,
+        dest_box.y - BOX_GAP / 2.0,
+    ];
+
+    let mut candidates = Vec::new();
+    for x in xs {
+        candidates.push(vec![
+            src,
+            src_out,
+            vec2(x, src_out.y),
+            vec2(x, dest_in.y),
+            dest_in,
+            dest,
+        ]);
+    }
+    for y in ys {
+        candidates.push(vec![
+            src,
+            src_out,
+            vec2(src_out.x, y),
+            vec2(dest_in.x, y),
+            dest_in,
+            dest,
+        ]);
+    }
+
+    // Ties are broken by order so the result is deterministic.
+    let mut best = None;
+    let mut best_cost = f32::INFINITY;
+    for candidate in candidates {
+        let points = simplify(candidate);
+        let cost = cost(&points, obstacles);
+        if cost < best_cost {
+            best_cost = cost;
+            best = Some(points);
+        }
+    }
+
+    Route {
+        style,
+        points: best.unwrap(),
+        arrow: Some(in_dir),
+        label,
+        label_pos,
+    }
+}
+
+/// Which way the edge leaves its port.
+fn exit_direction(graph: &ObjectGraph, edge: &Edge) -> Vec2 {
+    if is_cons_row(graph, edge) {
+        vec2(1.0, 0.0)
+    } else {
+        match edge.kind {
+            EdgeKind::Attrib => vec2(-1.0, 0.0),
+            EdgeKind::Payload(_) => vec2(0.0, 1.0),
+        }
+    }
+}
+
+/// Spread out edges entering the same box so they don't overlap completely.
+fn entry_nudge(edge: &Edge) -> Vec2 {
+    match edge.kind {
+        EdgeKind::Attrib => vec2(20.0, 0.0),
+        EdgeKind::Payload(i) => vec2(40.0 + (20.0 * i as f32) % (BOX_WIDTH - 80.0), 0.0),
+    }
+}
+
+fn label_position(port: Vec2, out_dir: Vec2, label: &str) -> Vec2 {
+    if out_dir.x < 0.0 {
+        // Left of the port, above the line
+        port + vec2(
+            -(label.chars().count() as f32) * LABEL_CHAR_WIDTH - 4.0,
+            -4.0,
+        )
+    } else if out_dir.x > 0.0 {
+        port + vec2(4.0, -4.0)
+    } else {
+        // Next to the line, just under the port
+        port + vec2(4.0, LABEL_FONT_SIZE)
+    }
+}
+
+/// Remove zero-length segments and merge collinear ones.
+fn simplify(points: Vec<Vec2>) -> Vec<Vec2> {
+    let mut result: Vec<Vec2> = Vec::new();
+    for point in points {
+        if result.last() == Some(&point) {
+            continue;
+        }
+        if result.len() >= 2 {
+            let a = result[result.len() - 2];
+            let b = result[result.len() - 1];
+            let collinear = (a.x == b.x && b.x == point.x) || (a.y == b.y && b.y == point.y);
+            if collinear {
+                result.pop();
+            }
+        }
+        result.push(point);
+    }
+    result
+}
+
+/// Crossing boxes is much worse than a longer route, bends are slightly worse.
+fn cost(points: &[Vec2], obstacles: &Obstacles) -> f32 {
+    let mut cost = 0.0;
+    for segment in points.windows(2) {
+        cost += obstacles.crossings(segment[0], segment[1]) as f32 * 100_000.0;
+        cost += (segment[1] - segment[0]).abs().max_element();
+        cost += 50.0;
+    }
+    cost
+}
+
+/// Boxes sorted into a grid so we only test the ones near a segment.
+struct Obstacles {
+    grid: HashMap<(i32, i32), Vec<Rect>>,
+}
+
+impl Obstacles {
+    fn new(positions: &HashMap<Sexp, Vec2>) -> Self {
+        let mut grid: HashMap<_, Vec<_>> = HashMap::new();
+        for pos in positions.values() {
+            // Shrink slightly so segments running along an edge or touching a port don't count.
+            let rect = Rect::new(pos.x + 1.0, pos.y + 1.0, BOX_WIDTH - 2.0, BOX_HEIGHT - 2.0);
+            for cell in cells(rect.point(), rect.point() + rect.size()) {
+                grid.entry(cell).or_insert_with(Vec::new).push(rect);
+            }
+        }
+        Self { grid }
+    }
+
+    /// How many boxes the axis-aligned segment crosses.
+    fn crossings(&self, a: Vec2, b: Vec2) -> usize {
+        let min = a.min(b);
+        let max = a.max(b);
+        let mut crossed = Vec::new();
+        for cell in cells(min, max) {
+            if let Some(rects) = self.grid.get(&cell) {
+                for rect in rects {
+                    let hit = min.x < rect.right()
+                        && max.x > rect.left()
+                        && min.y < rect.bottom()
+                        && max.y > rect.top();
+                    // A box can be in multiple cells.
+                    if hit && !crossed.contains(rect) {
+                        crossed.push(*rect);
+                    }
+                }
+            }
+        }
+        crossed.len()
+    }
+}
+
+fn cells(min: Vec2, max: Vec2) -> impl Iterator<Item = (i32, i32)> {
+    let x0 = (min.x / GRID_CELL).floor() as i32;
+    let x1 = (max.x / GRID_CELL).floor() as i32;
+    let y0 = (min.y / GRID_CELL).floor() as i32;
+    let y1 = (max.y / GRID_CELL).floor() as i32;
+    (x0..=x1).flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::testing::*;
+
+    use super::*;
+    use crate::layout::port_offset;
+
+    /// `x <- pairlist(1)` with a second root `y` to put in the way.
+    fn graph() -> ObjectGraph {
+        ObjectGraph::new(update(
+            &[("x", 0x100), ("y", 0x300)],
+            vec![
+                list(0x100, Sexp(0x200), NIL, NIL),
+                real(0x200, &["1"]),
+                real(0x300, &["2"]),
+                nil(),
+            ],
+        ))
+    }
+
+    fn positions(sexps: &[(u64, f32, f32)]) -> HashMap<Sexp, Vec2> {
+        sexps
+            .iter()
+            .map(|&(sexp, x, y)| (Sexp(sexp), vec2(x, y)))
+            .collect()
+    }
+
+    fn find<'a>(routes: &'a [Route], label: &str) -> &'a Route {
+        routes
+            .iter()
+            .find(|route| route.label.starts_with(label))
+            .unwrap()
+    }
+
+    #[test]
+    fn simplify_merges_collinear() {
+        let points = vec![
+            vec2(0.0, 0.0),
+            vec2(0.0, 0.0),
+            vec2(0.0, 10.0),
+            vec2(0.0, 20.0),
+            vec2(10.0, 20.0),
+            vec2(20.0, 20.0),
+            vec2(20.0, 30.0),
+        ];
+        assert_eq!(
+            simplify(points),
+            [
+                vec2(0.0, 0.0),
+                vec2(0.0, 20.0),
+                vec2(20.0, 20.0),
+                vec2(20.0, 30.0)
+            ]
+        );
+        assert_eq!(simplify(vec![vec2(1.0, 1.0); 3]), [vec2(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn crossings() {
+        let obstacles = Obstacles::new(&positions(&[(1, 0.0, 0.0), (2, 2000.0, 0.0)]));
+        let y = BOX_HEIGHT / 2.0;
+        assert_eq!(obstacles.crossings(vec2(-100.0, y), vec2(100.0, y)), 1);
+        // Spans several grid cells, both boxes count once.
+        assert_eq!(obstacles.crossings(vec2(-100.0, y), vec2(5000.0, y)), 2);
+        // Along the border or touching a port.
+        assert_eq!(obstacles.crossings(vec2(0.0, 0.0), vec2(BOX_WIDTH, 0.0)), 0);
+        let port = vec2(100.0, BOX_HEIGHT);
+        assert_eq!(obstacles.crossings(port, port + vec2(0.0, 50.0)), 0);
+        // Between the boxes.
+        assert_eq!(
+            obstacles.crossings(vec2(1500.0, -100.0), vec2(1500.0, 500.0)),
+            0
+        );
+    }
+
+    #[test]
+    fn route_avoids_boxes() {
+        let graph = graph();
+        let level = BOX_HEIGHT + BOX_GAP;
+        // y is right between x and its carval.
+        let positions = positions(&[
+            (0x100, 0.0, 0.0),
+            (0x300, 0.0, level),
+            (0x200, 0.0, 2.0 * level),
+        ]);
+        let obstacles = Obstacles::new(&positions);
+
+        let routes = route_edges(&graph, &positions);
+        let route = find(&routes, "carval");
+        assert_eq!(route.style, EdgeStyle::Payload);
+        assert_eq!(route.arrow, Some(vec2(0.0, 1.0)));
+        assert!(route.points.len() > 2);
+        for segment in route.points.windows(2) {
+            assert_eq!(obstacles.crossings(segment[0], segment[1]), 0);
+            // Orthogonal
+            assert!(segment[0].x == segment[1].x || segment[0].y == segment[1].y);
+        }
+        let (src_offset, dest_offset) = edge_offsets(&graph, &graph.outgoing(Sexp(0x100))[1]);
+        assert_eq!(route.points[0], positions[&Sexp(0x100)] + src_offset);
+        assert_eq!(
+            route.points.last().unwrap().y,
+            positions[&Sexp(0x200)].y + dest_offset.y
+        );
+    }
+
+    #[test]
+    fn globals_are_stubs() {
+        let graph = graph();
+        let mut positions = positions(&[(0x100, 0.0, 0.0), (0x200, 0.0, 500.0)]);
+        positions.insert(NIL, vec2(-2000.0, 1000.0));
+
+        let routes = route_edges(&graph, &positions);
+        let cdr = find(&routes, "cdrval");
+        assert_eq!(cdr.style, EdgeStyle::Global);
+        assert_eq!(cdr.label, "cdrval → R_NilValue");
+        assert_eq!(cdr.arrow, None);
+        // Straight down from the port, nowhere near R_NilValue's box.
+        let port = vec2(0.0, 0.0) + port_offset(EdgeKind::Payload(1));
+        assert_eq!(cdr.points, [port, port + vec2(0.0, GLOBAL_STUB)]);
+        assert_eq!(find(&routes, "attrib").style, EdgeStyle::Global);
+
+        // Without a position, there's nothing to route to.
+        positions.remove(&NIL);
+        let routes = route_edges(&graph, &positions);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].style, EdgeStyle::Payload);
+    }
+}
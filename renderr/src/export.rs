@@ -0,0 +1,39 @@
+//! The subcommands.
+
+use std::{fs, io::Write, path::Path};
+
+use commonr::dot;
+use renderr::{
+    load_file,
+    svg::{self, SvgOptions},
+};
+
+use crate::cli::{DotArgs, SvgArgs};
+
+pub fn dot(args: &DotArgs) -> Result<(), String> {
+    let file = load_file(&args.file)?;
+    let graph = dot::to_dot(&file.update, &args.options);
+    write(args.output.as_deref(), &graph)
+}
+
+pub fn svg(args: &SvgArgs) -> Result<(), String> {
+    let file = load_file(&args.file)?;
+    let saved_zoom = file.view.camera.as_ref().map(|camera| camera.zoom);
+    let options = SvgOptions {
+        zoom: args.zoom.or(saved_zoom).unwrap_or(1.0),
+        scheme: args.scheme,
+    };
+    let image = svg::render(&file.update, &file.view, &options);
+    write(args.output.as_deref(), &image)
+}
+
+/// To the file or stdout if there's none.
+fn write(output: Option<&Path>, contents: &str) -> Result<(), String> {
+    match output {
+        Some(path) => fs::write(path, contents)
+            .map_err(|err| format!("failed to save {}: {}", path.display(), err)),
+        None => std::io::stdout()
+            .write_all(contents.as_bytes())
+            .map_err(|err| err.to_string()),
+    }
+}
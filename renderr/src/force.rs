@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+
+use commonr::{data::Sexp, graph::ObjectGraph};
+
+use crate::{
+    geometry::{vec2, Vec2},
+    layout::{BOX_GAP, BOX_HEIGHT, BOX_WIDTH},
+};
+
+/// How far apart connected nodes want to be (between centers).
+const SPRING_LENGTH: f32 = BOX_WIDTH + BOX_GAP;
+const SPRING_STRENGTH: f32 = 0.02;
+const REPULSION: f32 = 4.0e6;
+/// Nodes further apart than this don't repel each other.
+/// Also the size of the cells of the spatial grid.
+const REPULSION_RANGE: f32 = 3.0 * BOX_WIDTH;
+const DAMPING: f32 = 0.8;
+const MAX_SPEED: f32 = 200.0;
+/// Below this average speed the simulation is considered settled.
+const SETTLED: f32 = 0.5;
+
+/// Force-directed layout for graphs which aren't trees - environments, closures, namespaces.
+///
+/// Connected nodes are pulled together by springs, all nodes push each other apart
+/// and overlapping boxes are separated.
+/// Globals don't move and edges into them are ignored,
+/// otherwise everything would clump around R_NilValue.
+///
+/// Meant to be run incrementally - call `step` every frame until it settles.
+/// It starts from whatever positions it's given, usually the tree layout.
+#[derive(Debug, Default)]
+pub struct ForceLayout {
+    velocities: HashMap<Sexp, Vec2>,
+    settled: bool,
+}
+
+impl ForceLayout {
+    /// Forget velocities and start simulating again, e.g. when the graph changes.
+    pub fn reset(&mut self) {
+        self.velocities.clear();
+        self.settled = false;
+    }
+
+    /// Start simulating again without forgetting velocities, e.g. when the user moves a node.
+    pub fn wake(&mut self) {
+        self.settled = false;
+    }
+
+    /// Nothing moves anymore, there's no need to call `step` until something changes.
+    #[must_use]
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Run one iteration of the simulation.
+    /// Pinned nodes exert forces but don't move.
+    ///
+    /// Returns whether anything still moves.
+    pub fn step(
+        &mut self,
+        graph: &ObjectGraph,
+        positions: &mut HashMap<Sexp, Vec2>,
+        pinned: &HashSet<Sexp>,
+    ) -> bool {
+        let half = vec2(BOX_WIDTH, BOX_HEIGHT) / 2.0;
+
+        // Deterministic order so the same graph always ends up the same.
+        let mut sexps: Vec<_> = positions.keys().copied().collect();
+        sexps.sort_by_key(|sexp| sexp.0);
+        let centers: Vec<_> = sexps.iter().map(|sexp| positions[sexp] + half).collect();
+        let indices: HashMap<_, _> = sexps.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, center) in centers.iter().enumerate() {
+            grid.entry(cell(*center)).or_default().push(i);
+        }
+
+        let mut forces = vec![Vec2::ZERO; sexps.len()];
+
+        // Repulsion and collisions between nearby nodes
+        for (i, &center) in centers.iter().enumerate() {
+            let (cx, cy) = cell(center);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let others = match grid.get(&(cx + dx, cy + dy)) {
+                        Some(others) => others,
+                        None => continue,
+                    };
+                    for &j in others {
+                        if j == i {
+                            continue;
+                        }
+                        let mut delta = center - centers[j];
+                        if delta == Vec2::ZERO {
+                            // Exactly on top of each other, push apart in an arbitrary but stable direction.
+                            delta = vec2(if i < j { -1.0 } else { 1.0 }, 0.0);
+                        }
+                        let dist = delta.length().max(1.0);
+                        if dist > REPULSION_RANGE {
+                            continue;
+                        }
+                        forces[i] += delta / dist * REPULSION / (dist * dist);
+
+                        let overlap = 2.0 * half + vec2(BOX_GAP, BOX_GAP) / 2.0 - delta.abs();
+                        if overlap.x > 0.0 && overlap.y > 0.0 {
+                            // Separate along the axis that needs less movement.
+                            if overlap.x < overlap.y {
+                                forces[i].x += overlap.x.copysign(delta.x) * 0.5;
+                            } else {
+                                forces[i].y += overlap.y.copysign(delta.y) * 0.5;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Springs along edges
+        for (i, &sexp) in sexps.iter().enumerate() {
+            for edge in graph.outgoing(sexp) {
+                if graph.is_global(edge.dest) || edge.dest == sexp {
+                    continue;
+                }
+                let j = match indices.get(&edge.dest) {
+                    Some(&j) => j,
+                    None => continue,
+                };
+                let delta = centers[j] - centers[i];
+                let dist = delta.length().max(1.0);
+                let force = delta / dist * (dist - SPRING_LENGTH) * SPRING_STRENGTH;
+                forces[i] += force;
+                forces[j] -= force;
+            }
+        }
+
+        let mut total_speed = 0.0;
+        let mut moving = 0;
+        for (i, &sexp) in sexps.iter().enumerate() {
+            if pinned.contains(&sexp) || graph.is_global(sexp) {
+                self.velocities.remove(&sexp);
+                continue;
+            }
+            let velocity = self.velocities.entry(sexp).or_insert(Vec2::ZERO);
+            *velocity = ((*velocity + forces[i]) * DAMPING).clamp_length_max(MAX_SPEED);
+            *positions.get_mut(&sexp).unwrap() += *velocity;
+            total_speed += velocity.length();
+            moving += 1;
+        }
+
+        let still_moving = moving > 0 && total_speed / moving as f32 > SETTLED;
+        self.settled = !still_moving;
+        still_moving
+    }
+}
+
+/// Nodes which the force-directed layout doesn't move - roots and nodes moved by the user.
+#[must_use]
+pub fn pinned(graph: &ObjectGraph, manual: &HashMap<Sexp, Vec2>) -> HashSet<Sexp> {
+    let roots = graph.roots().iter().map(|root| root.sexp);
+    roots.chain(manual.keys().copied()).collect()
+}
+
+fn cell(pos: Vec2) -> (i32, i32) {
+    (
+        (pos.x / REPULSION_RANGE).floor() as i32,
+        (pos.y / REPULSION_RANGE).floor() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::{data::Root, testing::*};
+
+    use super::*;
+    use crate::layout::{Layout, TidyTree};
+
+    /// Plenty for the small graphs here, the window runs a few hundred per second.
+    const MAX_STEPS: usize = 10_000;
+
+    /// Steps until settled, None if it never does.
+    fn settle(
+        force: &mut ForceLayout,
+        graph: &ObjectGraph,
+        positions: &mut HashMap<Sexp, Vec2>,
+        pinned: &HashSet<Sexp>,
+    ) -> Option<usize> {
+        (1..=MAX_STEPS).find(|_| !force.step(graph, positions, pinned))
+    }
+
+    /// Reals which aren't connected to each other.
+    fn unconnected(positions: &[(u64, f32, f32)]) -> (ObjectGraph, HashMap<Sexp, Vec2>) {
+        let mut sexprecs: Vec<_> = positions
+            .iter()
+            .map(|&(sexp, _, _)| real(sexp, &["1"]))
+            .collect();
+        sexprecs.push(nil());
+        let graph = ObjectGraph::new(update(&[("x", positions[0].0)], sexprecs));
+        let positions = positions
+            .iter()
+            .map(|&(sexp, x, y)| (Sexp(sexp), vec2(x, y)))
+            .collect();
+        (graph, positions)
+    }
+
+    #[test]
+    fn pinned_nodes_stay() {
+        let mut update = pairlist();
+        update.roots.push(Root {
+            name: "y".to_owned(),
+            sexp: Sexp(0x500),
+        });
+        update.sexprecs.push(list(0x500, Sexp(0x400), NIL, NIL));
+        let graph = ObjectGraph::new(update);
+        let mut positions = TidyTree::default().layout(&graph, &HashSet::new());
+        let manual = [(Sexp(0x300), positions[&Sexp(0x300)])]
+            .into_iter()
+            .collect();
+        let pinned = pinned(&graph, &manual);
+        assert_eq!(
+            pinned,
+            [Sexp(0x100), Sexp(0x300), Sexp(0x500)]
+                .into_iter()
+                .collect()
+        );
+
+        let before = positions.clone();
+        let mut force = ForceLayout::default();
+        settle(&mut force, &graph, &mut positions, &pinned).unwrap();
+        for sexp in [0x100, 0x300, 0x500] {
+            assert_eq!(positions[&Sexp(sexp)], before[&Sexp(sexp)]);
+        }
+        // Globals never move either.
+        assert_eq!(positions[&NIL], before[&NIL]);
+        assert_ne!(positions[&Sexp(0x200)], before[&Sexp(0x200)]);
+    }
+
+    #[test]
+    fn settles() {
+        let graph = ObjectGraph::new(pairlist());
+        let mut positions = TidyTree::default().layout(&graph, &HashSet::new());
+        let pinned = pinned(&graph, &HashMap::new());
+
+        let mut force = ForceLayout::default();
+        assert!(!force.is_settled());
+        settle(&mut force, &graph, &mut positions, &pinned).unwrap();
+        assert!(force.is_settled());
+
+        // Settled means it stays settled, nothing is pushing anymore.
+        let settled = positions.clone();
+        force.wake();
+        assert!(!force.is_settled());
+        assert_eq!(settle(&mut force, &graph, &mut positions, &pinned), Some(1));
+        let moved = settled
+            .iter()
+            .map(|(sexp, pos)| (positions[sexp] - *pos).length())
+            .fold(0.0, f32::max);
+        assert!(moved < 1.0, "moved by {}", moved);
+
+        // Same input, same output.
+        let mut again = TidyTree::default().layout(&graph, &HashSet::new());
+        let mut force = ForceLayout::default();
+        settle(&mut force, &graph, &mut again, &pinned).unwrap();
+        assert_eq!(again, settled);
+    }
+
+    #[test]
+    fn repulsion() {
+        // Overlapping, with the centers in different grid cells.
+        let x = REPULSION_RANGE - BOX_WIDTH / 2.0 - 100.0;
+        let (graph, mut positions) = unconnected(&[(0x100, x, 0.0), (0x200, x + 200.0, 0.0)]);
+        let before = positions.clone();
+        let mut force = ForceLayout::default();
+        assert!(force.step(&graph, &mut positions, &HashSet::new()));
+        assert!(positions[&Sexp(0x100)].x < before[&Sexp(0x100)].x);
+        assert!(positions[&Sexp(0x200)].x > before[&Sexp(0x200)].x);
+
+        // Pushed apart until they don't overlap anymore.
+        settle(&mut force, &graph, &mut positions, &HashSet::new()).unwrap();
+        let distance = positions[&Sexp(0x200)].x - positions[&Sexp(0x100)].x;
+        assert!(distance >= BOX_WIDTH, "{}", distance);
+    }
+
+    #[test]
+    fn no_repulsion_out_of_range() {
+        let far = 3.0 * REPULSION_RANGE;
+        let (graph, mut positions) = unconnected(&[(0x100, 0.0, 0.0), (0x200, far, 0.0)]);
+        let before = positions.clone();
+        let mut force = ForceLayout::default();
+        assert!(!force.step(&graph, &mut positions, &HashSet::new()));
+        assert_eq!(positions, before);
+    }
+
+    #[test]
+    fn same_position() {
+        let (graph, mut positions) = unconnected(&[(0x100, 0.0, 0.0), (0x200, 0.0, 0.0)]);
+        let mut force = ForceLayout::default();
+        force.step(&graph, &mut positions, &HashSet::new());
+        assert!(positions[&Sexp(0x100)].x < positions[&Sexp(0x200)].x);
+        assert_eq!(positions[&Sexp(0x100)].y, positions[&Sexp(0x200)].y);
+    }
+}
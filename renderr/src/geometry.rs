@@ -0,0 +1,108 @@
+//! The few math and color types the layout and rendering need, macroquad has its own versions.
+//!
+//! `Vec2` is glam's, the same version macroquad uses, so positions can be passed to the window as is.
+//! `Color` and `Rect` have the same fields as macroquad's.
+
+pub use glam::{vec2, Vec2};
+
+/// RGBA, each 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    #[must_use]
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+/// Truncated like macroquad does.
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        let byte = |c: f32| (c * 255.0) as u8;
+        [byte(color.r), byte(color.g), byte(color.b), byte(color.a)]
+    }
+}
+
+// Same values as macroquad's.
+pub const WHITE: Color = Color::new(1.00, 1.00, 1.00, 1.00);
+pub const BLACK: Color = Color::new(0.00, 0.00, 0.00, 1.00);
+pub const GRAY: Color = Color::new(0.51, 0.51, 0.51, 1.00);
+pub const DARKGRAY: Color = Color::new(0.31, 0.31, 0.31, 1.00);
+pub const YELLOW: Color = Color::new(0.99, 0.98, 0.00, 1.00);
+pub const ORANGE: Color = Color::new(1.00, 0.63, 0.00, 1.00);
+pub const RED: Color = Color::new(0.90, 0.16, 0.22, 1.00);
+pub const DARKGREEN: Color = Color::new(0.00, 0.46, 0.17, 1.00);
+pub const BLUE: Color = Color::new(0.00, 0.47, 0.95, 1.00);
+pub const DARKBLUE: Color = Color::new(0.00, 0.32, 0.67, 1.00);
+
+/// Axis-aligned, `x` and `y` is the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    #[must_use]
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// The top left corner.
+    #[must_use]
+    pub fn point(&self) -> Vec2 {
+        vec2(self.x, self.y)
+    }
+
+    #[must_use]
+    pub fn size(&self) -> Vec2 {
+        vec2(self.w, self.h)
+    }
+
+    #[must_use]
+    pub fn center(&self) -> Vec2 {
+        self.point() + self.size() / 2.0
+    }
+
+    #[must_use]
+    pub fn left(&self) -> f32 {
+        self.x
+    }
+
+    #[must_use]
+    pub fn right(&self) -> f32 {
+        self.x + self.w
+    }
+
+    #[must_use]
+    pub fn top(&self) -> f32 {
+        self.y
+    }
+
+    #[must_use]
+    pub fn bottom(&self) -> f32 {
+        self.y + self.h
+    }
+
+    #[must_use]
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.left()
+            && point.x < self.right()
+            && point.y < self.bottom()
+            && point.y >= self.top()
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::VecDeque, time::SystemTime};
+
+use commonr::data::{Update, ValidationReport};
+
+/// A received update which was good enough to draw.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Counts all snapshots ever received, not just the ones still kept.
+    pub number: usize,
+    pub received: SystemTime,
+    pub update: Update,
+    pub report: ValidationReport,
+}
+
+/// The last few snapshots, oldest first.
+///
+/// Memory is bounded by the number of snapshots, not their size -
+/// LATER limit the total number of nodes instead?
+#[derive(Debug)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+    received: usize,
+    /// Index of the snapshot being shown.
+    current: usize,
+    /// Index of the snapshot `current` is compared to, if any.
+    /// Never the same as `current`, there'd be nothing to compare.
+    base: Option<usize>,
+}
+
+impl History {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "history has to keep at least one snapshot");
+        Self {
+            snapshots: VecDeque::new(),
+            capacity,
+            received: 0,
+            current: 0,
+            base: None,
+        }
+    }
+
+    /// Add a new snapshot, dropping the oldest one if full.
+    /// The new snapshot becomes the current one.
+    pub fn push(&mut self, update: Update, report: ValidationReport) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+            self.base = self.base.and_then(|base| base.checked_sub(1));
+        }
+        self.received += 1;
+        self.snapshots.push_back(Snapshot {
+            number: self.received,
+            received: SystemTime::now(),
+            update,
+            report,
+        });
+        self.current = self.snapshots.len() - 1;
+    }
+
+    /// Oldest first.
+    pub fn snapshots(&self) -> impl ExactSizeIterator<Item = &Snapshot> {
+        self.snapshots.iter()
+    }
+
+    #[must_use]
+    pub fn current(&self) -> Option<&Snapshot> {
+        self.snapshots.get(self.current)
+    }
+
+    #[must_use]
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    #[must_use]
+    pub fn base(&self) -> Option<&Snapshot> {
+        self.snapshots.get(self.base?)
+    }
+
+    #[must_use]
+    pub fn base_index(&self) -> Option<usize> {
+        self.base
+    }
+
+    /// Compare to the snapshot before the current one or stop comparing.
+    pub fn toggle_base(&mut self) {
+        self.base = match self.base {
+            Some(_) => None,
+            None => self.current.checked_sub(1),
+        };
+    }
+
+    /// Compare to the snapshot at `index`, or stop comparing if it's already the base.
+    /// The current snapshot can't be its own base.
+    pub fn set_base(&mut self, index: usize) {
+        self.base = if self.base == Some(index) || index == self.current {
+            None
+        } else if index < self.snapshots.len() {
+            Some(index)
+        } else {
+            self.base
+        };
+    }
+
+    /// Switch to the snapshot at `index` (oldest is 0), returns whether it changed.
+    ///
+    /// Switching to the base stops comparing.
+    pub fn select(&mut self, index: usize) -> bool {
+        if index == self.current || index >= self.snapshots.len() {
+            return false;
+        }
+        self.current = index;
+        if self.base == Some(index) {
+            self.base = None;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::testing::*;
+
+    use super::*;
+
+    /// With `count` snapshots, the last one is current.
+    fn history(capacity: usize, count: usize) -> History {
+        let mut history = History::new(capacity);
+        for _ in 0..count {
+            history.push(pairlist(), ValidationReport::default());
+        }
+        history
+    }
+
+    fn numbers(history: &History) -> Vec<usize> {
+        history
+            .snapshots()
+            .map(|snapshot| snapshot.number)
+            .collect()
+    }
+
+    #[test]
+    fn capacity() {
+        let mut history = history(3, 2);
+        assert_eq!(numbers(&history), [1, 2]);
+        assert_eq!(history.current_index(), 1);
+
+        history.push(pairlist(), ValidationReport::default());
+        history.push(pairlist(), ValidationReport::default());
+        assert_eq!(numbers(&history), [2, 3, 4]);
+        assert_eq!(history.current_index(), 2);
+        assert_eq!(history.current().unwrap().number, 4);
+    }
+
+    #[test]
+    fn base_shifts() {
+        let mut history = history(3, 3);
+        history.toggle_base();
+        assert_eq!(history.base().unwrap().number, 2);
+
+        // The base moves down with its snapshot.
+        history.push(pairlist(), ValidationReport::default());
+        assert_eq!(history.base_index(), Some(0));
+        assert_eq!(history.base().unwrap().number, 2);
+
+        // Until it's dropped.
+        history.push(pairlist(), ValidationReport::default());
+        assert_eq!(history.base_index(), None);
+    }
+
+    #[test]
+    fn toggle_base() {
+        let mut history = history(3, 1);
+        // Nothing before the first one.
+        history.toggle_base();
+        assert_eq!(history.base_index(), None);
+
+        let mut history = self::history(3, 2);
+        history.toggle_base();
+        assert_eq!(history.base_index(), Some(0));
+        history.toggle_base();
+        assert_eq!(history.base_index(), None);
+    }
+
+    #[test]
+    fn select() {
+        let mut history = history(5, 3);
+        assert!(!history.select(2));
+        assert!(!history.select(3));
+        assert!(history.select(1));
+        assert_eq!(history.current().unwrap().number, 2);
+
+        // PageUp onto the base.
+        history.toggle_base();
+        assert_eq!(history.base_index(), Some(0));
+        assert!(history.select(0));
+        assert_eq!(history.base_index(), None);
+    }
+
+    #[test]
+    fn set_base() {
+        let mut history = history(5, 3);
+        history.set_base(0);
+        assert_eq!(history.base_index(), Some(0));
+        history.set_base(1);
+        assert_eq!(history.base_index(), Some(1));
+        // Clicking the base again stops comparing.
+        history.set_base(1);
+        assert_eq!(history.base_index(), None);
+
+        history.set_base(2);
+        assert_eq!(history.base_index(), None);
+        history.set_base(0);
+        history.set_base(7);
+        assert_eq!(history.base_index(), Some(0));
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::{HashMap, HashSet};
+
+use commonr::{
+    data::{Sexp, Sexptype},
+    graph::{Edge, EdgeKind, ObjectGraph},
+};
+
+use crate::geometry::{vec2, Rect, Vec2};
+
+// There's no way to const init a Vec2 because none of glam's ctors are marked const.
+pub const BOX_INIT_X: f32 = 500.0;
+pub const BOX_INIT_Y: f32 = 50.0;
+pub const BOX_WIDTH: f32 = 950.0;
+pub const BOX_HEIGHT: f32 = 290.0;
+pub const BOX_GAP: f32 = 100.0;
+
+/// Where edges of the given kind leave the box, relative to its top left corner.
+pub fn port_offset(kind: EdgeKind) -> Vec2 {
+    match kind {
+        EdgeKind::Attrib => vec2(0.0, 200.0),
+        // Vectors can have a hundred elements, wrap around so the ports stay on the box.
+        EdgeKind::Payload(i) => vec2(10.0 + (300.0 * i as f32) % (BOX_WIDTH - 20.0), BOX_HEIGHT),
+    }
+}
+
+/// Where the edge leaves its source and where it enters its destination,
+/// both relative to the top left corner of the respective box.
+///
+/// Pairlist rows go out the right side and in the left side,
+/// everything else goes out its port and in the top.
+pub fn edge_offsets(graph: &ObjectGraph, edge: &Edge) -> (Vec2, Vec2) {
+    if is_cons_row(graph, edge) {
+        (
+            vec2(BOX_WIDTH, BOX_HEIGHT / 2.0),
+            vec2(0.0, BOX_HEIGHT / 2.0),
+        )
+    } else {
+        (port_offset(edge.kind), Vec2::ZERO)
+    }
+}
+
+/// Cons cells - the parts of pairlists and calls.
+fn is_cons(graph: &ObjectGraph, sexp: Sexp) -> bool {
+    match graph.get(sexp) {
+        Some(sexprec) => matches!(
+            sexprec.ty,
+            Sexptype::LISTSXP | Sexptype::LANGSXP | Sexptype::DOTSXP
+        ),
+        None => false,
+    }
+}
+
+/// Whether the edge is a cdrval between two cons cells.
+///
+/// These are laid out in a row, with carval and tagval hanging below each cell
+/// like in the classic cons cell diagrams.
+/// Otherwise lists and function bodies turn into a deep diagonal staircase.
+pub fn is_cons_row(graph: &ObjectGraph, edge: &Edge) -> bool {
+    // cdrval is the second payload pointer.
+    edge.kind == EdgeKind::Payload(1) && is_cons(graph, edge.src) && is_cons(graph, edge.dest)
+}
+
+/// A layout algorithm - computes where to put each node.
+pub trait Layout {
+    /// Returns the top left corner of every node in the graph except `hidden` ones.
+    fn layout(&mut self, graph: &ObjectGraph, hidden: &HashSet<Sexp>) -> HashMap<Sexp, Vec2>;
+}
+
+/// Top-down tree layout which keeps children in port order.
+///
+/// We probably can't use a general DAG layout algo because
+/// we want the edges to originate from specific parts of the node
+/// and we don't want to reorder children.
+/// Just in case, this looks interesting: https://reposhub.com/javascript/data-visualization/erikbrinkman-d3-dag.html#examples
+///
+/// The graph is turned into a spanning forest by DFS in port order,
+/// each node belongs to the first parent that reaches it.
+/// Every subtree gets its own horizontal interval so nothing overlaps.
+/// Edges to nodes owned by another parent (shared nodes, back-edges) get no space,
+/// they're just drawn.
+///
+/// Pairlists are an exception - see `is_cons_row`.
+///
+/// Globals are put in a column on the left.
+/// Originally i wanted to put them at the bottom but they were hard to find if the tree was large.
+#[derive(Debug, Default)]
+pub struct TidyTree {
+    /// Children in the spanning forest, in port order.
+    children: HashMap<Sexp, Vec<Sexp>>,
+    /// The next cons cell in a pairlist row, placed to the right instead of below.
+    row_next: HashMap<Sexp, Sexp>,
+    /// Width of each subtree including the node itself but without the trailing gap.
+    /// For cons cells this includes the rest of the row.
+    widths: HashMap<Sexp, f32>,
+    /// Horizontal offset of each child relative to its parent.
+    offsets: HashMap<Sexp, f32>,
+}
+
+impl Layout for TidyTree {
+    fn layout(&mut self, graph: &ObjectGraph, hidden: &HashSet<Sexp>) -> HashMap<Sexp, Vec2> {
+        self.children.clear();
+        self.row_next.clear();
+        self.widths.clear();
+        self.offsets.clear();
+
+        // User roots first, then whatever is only reachable through globals
+        // (e.g. the contents of R_GlobalEnv).
+        let mut tree_roots = Vec::new();
+        // Hidden nodes are treated as already claimed so they get no space.
+        let mut claimed = hidden.clone();
+        for root in graph.roots() {
+            if !graph.is_global(root.sexp) && claimed.insert(root.sexp) {
+                self.claim(graph, root.sexp, &mut claimed);
+                tree_roots.push(root.sexp);
+            }
+        }
+        let globals: Vec<_> = graph
+            .sexprecs()
+            .iter()
+            .map(|sexprec| sexprec.address)
+            .filter(|&sexp| graph.is_global(sexp))
+            .collect();
+        for &global in &globals {
+            for edge in graph.outgoing(global) {
+                if !graph.is_global(edge.dest) && claimed.insert(edge.dest) {
+                    self.claim(graph, edge.dest, &mut claimed);
+                    tree_roots.push(edge.dest);
+                }
+            }
+        }
+
+        for &tree_root in &tree_roots {
+            self.measure(graph, tree_root);
+        }
+
+        let mut positions = HashMap::new();
+        let mut pos = vec2(BOX_INIT_X, BOX_INIT_Y);
+        let mut bottom = pos.y;
+        for &tree_root in &tree_roots {
+            bottom = bottom.max(self.place(tree_root, pos, &mut positions));
+            pos.x += self.widths[&tree_root] + BOX_GAP;
+        }
+
+        let mut global_pos = vec2(
+            BOX_INIT_X - BOX_WIDTH - 2.0 * BOX_GAP,
+            BOX_INIT_Y + BOX_HEIGHT + BOX_GAP,
+        );
+        for global in globals {
+            positions.entry(global).or_insert_with(|| {
+                let pos = global_pos;
+                global_pos.y += BOX_HEIGHT + BOX_GAP;
+                pos
+            });
+        }
+
+        // Anything left shouldn't be reachable at all but the update comes from outside
+        // so put it in a row under everything else.
+        let mut rest_pos = vec2(BOX_INIT_X, bottom + BOX_GAP);
+        for sexprec in graph.sexprecs() {
+            if hidden.contains(&sexprec.address) {
+                continue;
+            }
+            positions.entry(sexprec.address).or_insert_with(|| {
+                let pos = rest_pos;
+                rest_pos.x += BOX_WIDTH + BOX_GAP;
+                pos
+            });
+        }
+
+        positions
+    }
+}
+
+impl TidyTree {
+    /// Build the spanning tree under `start` which has already been claimed.
+    ///
+    /// Recursion only goes down, pairlist rows are walked in a loop
+    /// so long lists don't overflow the stack.
+    fn claim(&mut self, graph: &ObjectGraph, start: Sexp, claimed: &mut HashSet<Sexp>) {
+        let mut current = start;
+        loop {
+            // The rest of the row is claimed last so carval and tagval
+            // of each cell hang below that cell and not a later one.
+            let mut children = Vec::new();
+            let mut row_edge = None;
+            for edge in graph.outgoing(current) {
+                if is_cons_row(graph, edge) {
+                    row_edge = Some(edge);
+                } else if !graph.is_global(edge.dest) && claimed.insert(edge.dest) {
+                    children.push(edge.dest);
+                    self.claim(graph, edge.dest, claimed);
+                }
+            }
+            self.children.insert(current, children);
+
+            match row_edge {
+                Some(edge) if claimed.insert(edge.dest) => {
+                    self.row_next.insert(current, edge.dest);
+                    current = edge.dest;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Compute subtree widths and child offsets bottom-up.
+    ///
+    /// Children are pushed right so they start under their port if there's room,
+    /// this keeps edges short and vertical where possible.
+    fn measure(&mut self, graph: &ObjectGraph, start: Sexp) -> f32 {
+        let mut row = vec![start];
+        while let Some(&next) = self.row_next.get(row.last().unwrap()) {
+            row.push(next);
+        }
+
+        // Each cell's own width first, then add up the rest of the row from the end.
+        let mut own_widths = Vec::with_capacity(row.len());
+        for &current in &row {
+            let children = self.children[&current].clone();
+            let mut cursor = 0.0_f32;
+            for child in children {
+                let kind = graph
+                    .outgoing(current)
+                    .iter()
+                    .find(|edge| edge.dest == child)
+                    .unwrap()
+                    .kind;
+                let offset = cursor.max(port_offset(kind).x);
+                let width = self.measure(graph, child);
+                self.offsets.insert(child, offset);
+                cursor = offset + width + BOX_GAP;
+            }
+            own_widths.push(BOX_WIDTH.max(cursor - BOX_GAP));
+        }
+
+        let mut rest = None;
+        for (i, &current) in row.iter().enumerate().rev() {
+            let mut width = own_widths[i];
+            if let Some(rest_width) = rest {
+                self.offsets.insert(row[i + 1], width + BOX_GAP);
+                width += BOX_GAP + rest_width;
+            }
+            self.widths.insert(current, width);
+            rest = Some(width);
+        }
+        rest.unwrap()
+    }
+
+    /// Returns the bottom of the subtree.
+    fn place(&self, start: Sexp, start_pos: Vec2, positions: &mut HashMap<Sexp, Vec2>) -> f32 {
+        let mut bottom = start_pos.y + BOX_HEIGHT;
+        let mut current = start;
+        let mut pos = start_pos;
+        loop {
+            positions.insert(current, pos);
+            for &child in &self.children[&current] {
+                let child_pos = vec2(pos.x + self.offsets[&child], pos.y + BOX_HEIGHT + BOX_GAP);
+                bottom = bottom.max(self.place(child, child_pos, positions));
+            }
+            match self.row_next.get(&current) {
+                Some(&next) => {
+                    pos = vec2(pos.x + self.offsets[&next], pos.y);
+                    current = next;
+                }
+                None => break,
+            }
+        }
+        bottom
+    }
+}
+
+/// The rectangle containing all the boxes, None if there are none.
+#[must_use]
+pub fn bounds(positions: &HashMap<Sexp, Vec2>) -> Option<Rect> {
+    let mut iter = positions.values();
+    let first = *iter.next()?;
+    let (min, max) = iter.fold((first, first), |(min, max), &pos| {
+        (min.min(pos), max.max(pos))
+    });
+    let max = max + vec2(BOX_WIDTH, BOX_HEIGHT);
+    Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+/// Globals like R_BaseEnv can reach thousands of nodes, nobody wants to see those by default.
+#[must_use]
+pub fn default_collapsed(graph: &ObjectGraph) -> HashSet<Sexp> {
+    graph
+        .sexprecs()
+        .iter()
+        .map(|sexprec| sexprec.address)
+        .filter(|&sexp| graph.is_global(sexp))
+        .collect()
+}
+
+/// Whether the node has any children which could be hidden.
+#[must_use]
+pub fn collapsible(view: &ObjectGraph, collapsed: &HashSet<Sexp>, sexp: Sexp) -> bool {
+    collapsed.contains(&sexp)
+        || view
+            .outgoing(sexp)
+            .iter()
+            .any(|edge| !view.is_global(edge.dest) && edge.dest != sexp)
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::{data::Update, testing::*};
+
+    use super::*;
+
+    #[test]
+    fn globals_collapsed_by_default() {
+        let mut update = pairlist();
+        update
+            .sexprecs
+            .push(environment(4, Sexp(0x100), Sexp(5), NIL));
+        let graph = ObjectGraph::new(update);
+        let collapsed = default_collapsed(&graph);
+        assert_eq!(collapsed, [NIL, Sexp(4)].into_iter().collect());
+        // The root is reachable from the root itself, not only from R_GlobalEnv.
+        assert!(graph.hidden(&collapsed).is_empty());
+    }
+
+    #[test]
+    fn collapsible_nodes() {
+        let mut update = pairlist();
+        // Only points to itself and to globals.
+        update.sexprecs.push(list(0x500, Sexp(0x500), NIL, NIL));
+        let graph = ObjectGraph::new(update);
+        let none = HashSet::new();
+
+        assert!(collapsible(&graph, &none, Sexp(0x100)));
+        assert!(collapsible(&graph, &none, Sexp(0x300)));
+        assert!(!collapsible(&graph, &none, Sexp(0x200)));
+        assert!(!collapsible(&graph, &none, Sexp(0x500)));
+        assert!(!collapsible(&graph, &none, NIL));
+        // Collapsed nodes can always be expanded again.
+        let collapsed = [Sexp(0x200)].into_iter().collect();
+        assert!(collapsible(&graph, &collapsed, Sexp(0x200)));
+    }
+
+    fn layout(update: Update) -> HashMap<Sexp, Vec2> {
+        TidyTree::default().layout(&ObjectGraph::new(update), &HashSet::new())
+    }
+
+    fn overlap(a: Vec2, b: Vec2) -> bool {
+        (a.x - b.x).abs() < BOX_WIDTH && (a.y - b.y).abs() < BOX_HEIGHT
+    }
+
+    #[test]
+    fn siblings_dont_overlap() {
+        // list(pairlist(1, 2, 3), list(4, 5))
+        let update = update(
+            &[("x", 0x100)],
+            vec![
+                vector(0x100, Sexptype::VECSXP, &[Sexp(0x200), Sexp(0x300)]),
+                list(0x200, Sexp(0x210), Sexp(0x220), NIL),
+                real(0x210, &["1"]),
+                list(0x220, Sexp(0x230), Sexp(0x240), NIL),
+                real(0x230, &["2"]),
+                list(0x240, Sexp(0x250), NIL, NIL),
+                real(0x250, &["3"]),
+                vector(0x300, Sexptype::VECSXP, &[Sexp(0x310), Sexp(0x320)]),
+                real(0x310, &["4"]),
+                real(0x320, &["5"]),
+                nil(),
+            ],
+        );
+        let positions = layout(update);
+        assert_eq!(positions.len(), 11);
+
+        let all: Vec<_> = positions.iter().collect();
+        for (i, &(a, &pos_a)) in all.iter().enumerate() {
+            for &(b, &pos_b) in &all[i + 1..] {
+                assert!(!overlap(pos_a, pos_b), "{:?} and {:?} overlap", a, b);
+            }
+        }
+        // The first subtree is entirely left of the second one.
+        let right_of_first = [0x200, 0x210, 0x220, 0x230, 0x240, 0x250]
+            .iter()
+            .map(|&sexp| positions[&Sexp(sexp)].x + BOX_WIDTH)
+            .fold(f32::MIN, f32::max);
+        assert!(right_of_first <= positions[&Sexp(0x300)].x);
+        // Children are one level below their parent.
+        let level = BOX_HEIGHT + BOX_GAP;
+        assert_eq!(positions[&Sexp(0x200)].y, positions[&Sexp(0x100)].y + level);
+        assert_eq!(positions[&Sexp(0x310)].y, positions[&Sexp(0x300)].y + level);
+    }
+
+    #[test]
+    fn cons_rows() {
+        let positions = layout(pairlist());
+        let (first, car, rest, cadr) = (
+            positions[&Sexp(0x100)],
+            positions[&Sexp(0x200)],
+            positions[&Sexp(0x300)],
+            positions[&Sexp(0x400)],
+        );
+        // cdrval goes to the right, carval hangs below its own cell.
+        assert_eq!(rest.y, first.y);
+        assert!(rest.x >= first.x + BOX_WIDTH + BOX_GAP);
+        assert_eq!(car.y, first.y + BOX_HEIGHT + BOX_GAP);
+        assert_eq!(cadr.y, car.y);
+        assert_eq!(car.x, first.x + port_offset(EdgeKind::Payload(0)).x);
+        assert!(car.x + BOX_WIDTH <= cadr.x);
+        // R_NilValue is in the column of globals on the left.
+        assert!(positions[&NIL].x + BOX_WIDTH < first.x);
+    }
+
+    #[test]
+    fn long_pairlist() {
+        const LEN: u64 = 100_000;
+        let mut sexprecs: Vec<_> = (0..LEN)
+            .map(|i| {
+                let cdr = if i + 1 < LEN {
+                    Sexp(0x1000 + i + 1)
+                } else {
+                    NIL
+                };
+                list(0x1000 + i, NIL, cdr, NIL)
+            })
+            .collect();
+        sexprecs.push(nil());
+        let positions = layout(update(&[("x", 0x1000)], sexprecs));
+
+        assert_eq!(positions.len(), LEN as usize + 1);
+        let first = positions[&Sexp(0x1000)];
+        let second = positions[&Sexp(0x1001)];
+        assert_eq!(second.x - first.x, BOX_WIDTH + BOX_GAP);
+        for i in 1..LEN {
+            let prev = positions[&Sexp(0x1000 + i - 1)];
+            let pos = positions[&Sexp(0x1000 + i)];
+            assert_eq!(pos.y, first.y);
+            assert!(pos.x > prev.x);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Everything about showing updates which doesn't need a window - layout, edge routing,
+//! colors and what text goes in the boxes - so the command line exports build and run
+//! without macroquad and its system libraries. Visualizr only draws the results.
+
+pub mod args;
+pub mod colors;
+pub mod edges;
+pub mod force;
+pub mod geometry;
+pub mod history;
+pub mod layout;
+pub mod nodes;
+pub mod svg;
+
+use std::path::Path;
+
+use commonr::{
+    json,
+    snapshot::{self, SnapshotFile, ViewState},
+};
+
+/// Read a snapshot or JSON file (by extension).
+///
+/// JSON files have no view, they're shown as if they just arrived.
+pub fn load_file(path: &Path) -> Result<SnapshotFile, String> {
+    let is_json = path.extension().is_some_and(|ext| ext == json::EXTENSION);
+    let loaded = if is_json {
+        json::load(path)
+            .map(|update| SnapshotFile {
+                update,
+                view: ViewState::default(),
+            })
+            .map_err(|err| err.to_string())
+    } else {
+        snapshot::load(path).map_err(|err| err.to_string())
+    };
+    loaded.map_err(|err| format!("failed to open {}: {}", path.display(), err))
+}
@@ -0,0 +1,17 @@
+mod cli;
+mod export;
+
+use std::process;
+
+use crate::cli::Command;
+
+fn main() {
+    let result = match Command::parse() {
+        Command::Dot(args) => export::dot(&args),
+        Command::Svg(args) => export::svg(&args),
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
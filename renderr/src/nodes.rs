@@ -0,0 +1,196 @@
+use std::{collections::HashMap, fmt::Display};
+
+use commonr::{
+    data::{truncate, Sexp, SexpFormatter, SexpSummary, Sexprec},
+    graph::{ObjectGraph, RefCount},
+    semantic::describe,
+};
+
+use crate::{
+    geometry::{Color, Rect, Vec2, DARKGRAY, ORANGE, RED},
+    layout::BOX_WIDTH,
+};
+
+/// How much of each node to draw, depends on the zoom.
+///
+/// Editboxes are slow and unreadable when zoomed out,
+/// drawing hundreds of them makes the window unusable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Detail {
+    /// A colored rectangle with the type.
+    Chip,
+    /// The payload pointers and a few other fields (or the semantic description) drawn as plain text.
+    Summary,
+    /// The whole `SexpFormatter` dump (or the semantic description) in an editbox so it can be copied.
+    Full,
+}
+
+impl Detail {
+    /// Below this the editbox font is too small to read.
+    const FULL_ZOOM: f32 = 0.6;
+    /// Below this the summary font is too small to read.
+    const SUMMARY_ZOOM: f32 = 0.25;
+
+    #[must_use]
+    pub fn from_zoom(zoom: f32) -> Self {
+        if zoom >= Self::FULL_ZOOM {
+            Detail::Full
+        } else if zoom >= Self::SUMMARY_ZOOM {
+            Detail::Summary
+        } else {
+            Detail::Chip
+        }
+    }
+}
+
+/// In world units so it scales with the box.
+pub const SUMMARY_FONT_SIZE: f32 = 32.0;
+/// Of the editbox at zoom 1.
+pub const FULL_FONT_SIZE: f32 = 16.0;
+
+/// What to call the node when there's little space - the name for symbols, the type otherwise.
+#[must_use]
+pub fn title(graph: &ObjectGraph, sexprec: &Sexprec) -> String {
+    match graph.symbol_name(sexprec.address) {
+        Some(name) => format!("`{}`", truncate(name, 20)),
+        None => sexprec.ty.to_string(),
+    }
+}
+
+/// The text in the box at the given detail level.
+///
+/// In the `semantic` view, it's the semantic description at both `Summary` and `Full`.
+#[must_use]
+pub fn node_text(graph: &ObjectGraph, semantic: bool, sexprec: &Sexprec, detail: Detail) -> String {
+    let globals = graph.globals();
+    match detail {
+        Detail::Chip => title(graph, sexprec),
+        _ if semantic => describe(graph, sexprec.address).unwrap().to_string(),
+        Detail::Summary => with_name(graph, sexprec, SexpSummary(globals, sexprec)),
+        Detail::Full => with_name(graph, sexprec, SexpFormatter(globals, sexprec)),
+    }
+}
+
+/// Put the symbol's name above the dump of its SEXPREC.
+fn with_name(graph: &ObjectGraph, sexprec: &Sexprec, dump: impl Display) -> String {
+    match graph.symbol_name(sexprec.address) {
+        Some(name) => format!("symbol: {}\n{}", name, dump),
+        None => dump.to_string(),
+    }
+}
+
+/// Highlight nodes we couldn't fully decode (red) and nodes reachable from multiple roots (orange).
+#[must_use]
+pub fn frame_color(sexprec: &Sexprec, shared: &HashMap<Sexp, Vec<usize>>) -> Option<Color> {
+    if sexprec.ty.is_unknown() {
+        Some(RED)
+    } else if shared.contains_key(&sexprec.address) {
+        Some(ORANGE)
+    } else {
+        None
+    }
+}
+
+/// Drawn above the box - which roots it is, which roots share it and the user's note.
+#[must_use]
+pub fn node_labels(
+    graph: &ObjectGraph,
+    shared: &HashMap<Sexp, Vec<usize>>,
+    annotations: &HashMap<Sexp, String>,
+    sexp: Sexp,
+) -> Vec<String> {
+    let mut labels = Vec::new();
+    for root in graph.roots() {
+        if root.sexp == sexp {
+            labels.push(format!("root: {}", root.name));
+        }
+    }
+    if let Some(root_indices) = shared.get(&sexp) {
+        let names: Vec<_> = root_indices
+            .iter()
+            .map(|&i| graph.roots()[i].name.as_str())
+            .collect();
+        labels.push(format!("shared by: {}", names.join(", ")));
+    }
+    if let Some(text) = annotations.get(&sexp) {
+        labels.push(format!("note: {}", text));
+    }
+    labels
+}
+
+/// Keep labels readable when zoomed out, they're how you find your way around.
+#[must_use]
+pub fn label_font_size(zoom: f32) -> f32 {
+    (30.0 * zoom).max(16.0)
+}
+
+#[must_use]
+pub fn ref_count_font_size(zoom: f32) -> f32 {
+    28.0 * zoom
+}
+
+#[must_use]
+pub fn ref_count_label(in_degree: usize, count: RefCount) -> (String, Color) {
+    match count {
+        RefCount::Matches => (format!("refs: {}", in_degree), DARKGRAY),
+        RefCount::Outside(n) => (format!("refs: {} + {} outside", in_degree, n), ORANGE),
+        RefCount::Untracked(n) => (
+            format!("refs: {}, named: {}", in_degree, in_degree - n),
+            RED,
+        ),
+    }
+}
+
+/// Size of the collapse toggle in world units.
+pub const TOGGLE_SIZE: f32 = 50.0;
+
+/// Where the collapse toggle of a box at `box_pos` is, in world coordinates.
+///
+/// It's above the top right corner so it's not covered by the editbox.
+#[must_use]
+pub fn toggle_rect(box_pos: Vec2) -> Rect {
+    Rect::new(
+        box_pos.x + BOX_WIDTH - TOGGLE_SIZE,
+        box_pos.y - TOGGLE_SIZE - 10.0,
+        TOGGLE_SIZE,
+        TOGGLE_SIZE,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::testing::*;
+
+    use super::*;
+
+    #[test]
+    fn ref_count_labels() {
+        assert_eq!(
+            ref_count_label(1, RefCount::Matches),
+            ("refs: 1".to_owned(), DARKGRAY)
+        );
+        assert_eq!(
+            ref_count_label(1, RefCount::Outside(2)),
+            ("refs: 1 + 2 outside".to_owned(), ORANGE)
+        );
+        assert_eq!(
+            ref_count_label(3, RefCount::Untracked(2)),
+            ("refs: 3, named: 1".to_owned(), RED)
+        );
+    }
+
+    #[test]
+    fn ref_count_labels_from_graph() {
+        let mut update = pairlist();
+        update.sexprecs[1].sxpinfo.named = 0;
+        let graph = ObjectGraph::new(update);
+        let label = |address| {
+            let sexp = Sexp(address);
+            let in_degree = graph.incoming(sexp).len();
+            ref_count_label(in_degree, graph.ref_count(sexp).unwrap()).0
+        };
+        assert_eq!(label(0x100), "refs: 0 + 1 outside");
+        assert_eq!(label(0x200), "refs: 1, named: 0");
+        assert_eq!(label(0x300), "refs: 1");
+    }
+}
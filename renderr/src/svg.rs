@@ -0,0 +1,476 @@
+//! Render a snapshot to a standalone SVG without a window - for CI, scripts and docs.
+//!
+//! It's drawn the way the window would draw it at the given zoom - same layout, colors,
+//! labels and level of detail - except everything is visible, not just what fits on the screen.
+//! There's no selection, no diff and the editboxes of the `Full` level are plain text.
+//!
+//! The output only depends on the snapshot and the options (nodes are in the order
+//! they were received, numbers are rounded) so it can be used for golden-file tests.
+//! Text widths are estimated because there's no font to measure.
+
+use std::fmt::Write;
+
+use commonr::{
+    data::{Sexp, Update},
+    graph::ObjectGraph,
+    semantic::boxed,
+    snapshot::ViewState,
+};
+
+use crate::{
+    colors::{legend_height, pale, ColorScheme, LEGEND_FONT_SIZE, LEGEND_PADDING, LEGEND_SWATCH},
+    edges::{route_edges, Route, ARROW_SIZE, DASH, DASH_GAP, LABEL_FONT_SIZE},
+    geometry::{vec2, Color, Rect, Vec2, BLACK, DARKGRAY, WHITE, YELLOW},
+    layout::{bounds, collapsible, default_collapsed, Layout, TidyTree, BOX_HEIGHT, BOX_WIDTH},
+    nodes::{
+        frame_color, label_font_size, node_labels, node_text, ref_count_font_size, ref_count_label,
+        toggle_rect, Detail, FULL_FONT_SIZE, SUMMARY_FONT_SIZE,
+    },
+};
+
+/// Screen pixels around the graph, there are labels above the boxes and edge stubs around them.
+const MARGIN: f32 = 100.0;
+/// Rough width of a character relative to the font size, only used for placement.
+const CHAR_WIDTH: f32 = 0.55;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    /// Pixels per world unit, decides the level of detail like in the window.
+    pub zoom: f32,
+    pub scheme: ColorScheme,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            scheme: ColorScheme::Type,
+        }
+    }
+}
+
+/// The camera isn't used, the image always contains the whole graph.
+#[must_use]
+pub fn render(update: &Update, view_state: &ViewState, options: &SvgOptions) -> String {
+    let graph = ObjectGraph::new(update.clone());
+    let semantic = view_state
+        .semantic
+        .then(|| graph.contracted(&boxed(&graph)));
+    let view = semantic.as_ref().unwrap_or(&graph);
+
+    let collapsed = view_state
+        .collapsed
+        .clone()
+        .unwrap_or_else(|| default_collapsed(&graph));
+    let hidden = view.hidden(&collapsed);
+    let mut positions = TidyTree::default().layout(view, &hidden);
+    for (sexp, pos) in &mut positions {
+        if let Some(&[x, y]) = view_state.positions.get(sexp) {
+            *pos = vec2(x, y);
+        }
+    }
+    let routes = route_edges(view, &positions);
+
+    let zoom = options.zoom;
+    let world = bounds(&positions).unwrap_or_else(|| Rect::new(0.0, 0.0, BOX_WIDTH, BOX_HEIGHT));
+    let legend = options.scheme.legend();
+    let width = world.w * zoom + 2.0 * MARGIN;
+    let height = world.h * zoom + 2.0 * MARGIN + legend_height(legend);
+    let mut svg = Svg {
+        out: String::new(),
+        origin: world.point(),
+        zoom,
+    };
+
+    writeln!(
+        svg.out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {0} {1}" font-family="monospace" xml:space="preserve">"#,
+        num(width),
+        num(height)
+    )
+    .unwrap();
+    svg.rect(Rect::new(0.0, 0.0, width, height), WHITE, None);
+
+    // Edges first so they don't cover nodes, like in the window.
+    for route in &routes {
+        svg.route(route);
+    }
+
+    let detail = Detail::from_zoom(zoom);
+    let shared = graph.shared();
+    let size = vec2(BOX_WIDTH, BOX_HEIGHT) * zoom;
+    for sexprec in view.sexprecs() {
+        let world_pos = match positions.get(&sexprec.address) {
+            Some(&pos) => pos,
+            None => continue, // Hidden
+        };
+        let pos = svg.to_screen(world_pos);
+        let rect = Rect::new(pos.x, pos.y, size.x, size.y);
+
+        if collapsible(view, &collapsed, sexprec.address) {
+            let toggle = toggle_rect(world_pos);
+            let corner = svg.to_screen(toggle.point());
+            let toggle = Rect::new(corner.x, corner.y, toggle.w * zoom, toggle.h * zoom);
+            let is_collapsed = collapsed.contains(&sexprec.address);
+            let badge = if is_collapsed {
+                view.hidden_under(sexprec.address, &hidden)
+            } else {
+                0
+            };
+            svg.toggle(toggle, is_collapsed, badge);
+        }
+
+        let frame = frame_color(sexprec, &shared);
+        let color = options.scheme.color(&graph, sexprec);
+        let text = node_text(&graph, semantic.is_some(), sexprec, detail);
+        match detail {
+            Detail::Chip => {
+                svg.rect(rect, color, Some((frame.unwrap_or(DARKGRAY), 4.0)));
+                let font_size = size.y * 0.4;
+                if font_size >= 4.0 {
+                    let center = rect.center();
+                    svg.text(
+                        &text,
+                        vec2(center.x, center.y + font_size * 0.35),
+                        font_size,
+                        BLACK,
+                        "middle",
+                    );
+                }
+            }
+            Detail::Summary | Detail::Full => {
+                svg.rect(rect, pale(color), Some((frame.unwrap_or(DARKGRAY), 4.0)));
+                let font_size = match detail {
+                    Detail::Summary => SUMMARY_FONT_SIZE,
+                    _ => FULL_FONT_SIZE,
+                } * zoom;
+                svg.lines(sexprec.address, &text, rect, font_size);
+            }
+        }
+
+        if let Some(count) = graph.ref_count(sexprec.address) {
+            let font_size = ref_count_font_size(zoom);
+            if font_size >= 6.0 {
+                let in_degree = graph.incoming(sexprec.address).len();
+                let (text, color) = ref_count_label(in_degree, count);
+                let pos = vec2(rect.right(), rect.bottom() + font_size);
+                svg.text(&text, pos, font_size, color, "end");
+            }
+        }
+
+        let labels = node_labels(&graph, &shared, &view_state.annotations, sexprec.address);
+        if !labels.is_empty() {
+            let font_size = label_font_size(zoom);
+            let pos = vec2(pos.x, pos.y - font_size / 3.0);
+            svg.text(&labels.join(" | "), pos, font_size, BLACK, "start");
+        }
+    }
+
+    let title = format!("color: {}", options.scheme.name());
+    svg.legend(&title, legend, height);
+
+    writeln!(svg.out, "</svg>").unwrap();
+    svg.out
+}
+
+struct Svg {
+    out: String,
+    /// World position of the top left corner of the graph.
+    origin: Vec2,
+    zoom: f32,
+}
+
+impl Svg {
+    fn to_screen(&self, pos: Vec2) -> Vec2 {
+        (pos - self.origin) * self.zoom + vec2(MARGIN, MARGIN)
+    }
+
+    fn rect(&mut self, rect: Rect, fill: Color, stroke: Option<(Color, f32)>) {
+        write!(
+            self.out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}"#,
+            num(rect.x),
+            num(rect.y),
+            num(rect.w),
+            num(rect.h),
+            paint("fill", fill)
+        )
+        .unwrap();
+        if let Some((color, width)) = stroke {
+            write!(
+                self.out,
+                r#" {} stroke-width="{}""#,
+                paint("stroke", color),
+                num(width)
+            )
+            .unwrap();
+        }
+        writeln!(self.out, "/>").unwrap();
+    }
+
+    /// `pos` is the baseline, `anchor` is `start`, `middle` or `end`.
+    fn text(&mut self, text: &str, pos: Vec2, font_size: f32, color: Color, anchor: &str) {
+        write!(
+            self.out,
+            r#"<text x="{}" y="{}" font-size="{}" {}"#,
+            num(pos.x),
+            num(pos.y),
+            num(font_size),
+            paint("fill", color)
+        )
+        .unwrap();
+        if anchor != "start" {
+            write!(self.out, r#" text-anchor="{}""#, anchor).unwrap();
+        }
+        writeln!(self.out, ">{}</text>", escape(text)).unwrap();
+    }
+
+    /// Lines of text cut off at the edges of the box like `draw_summary` and the editbox do.
+    fn lines(&mut self, sexp: Sexp, text: &str, rect: Rect, font_size: f32) {
+        let id = format!("clip{:x}", sexp.0);
+        writeln!(
+            self.out,
+            r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+            id,
+            num(rect.x),
+            num(rect.y),
+            num(rect.w),
+            num(rect.h)
+        )
+        .unwrap();
+        writeln!(self.out, r#"<g clip-path="url(#{})">"#, id).unwrap();
+        let mut y = rect.y + font_size;
+        for line in text.lines() {
+            if y > rect.bottom() {
+                break;
+            }
+            self.text(
+                line,
+                vec2(rect.x + font_size / 3.0, y),
+                font_size,
+                BLACK,
+                "start",
+            );
+            y += font_size;
+        }
+        writeln!(self.out, "</g>").unwrap();
+    }
+
+    fn route(&mut self, route: &Route) {
+        let color = route.style.color();
+        let points: Vec<_> = route
+            .points
+            .iter()
+            .map(|&point| self.to_screen(point))
+            .collect();
+        let coords: Vec<_> = points
+            .iter()
+            .map(|point| format!("{},{}", num(point.x), num(point.y)))
+            .collect();
+        write!(
+            self.out,
+            r#"<polyline points="{}" fill="none" {} stroke-width="{}""#,
+            coords.join(" "),
+            paint("stroke", color),
+            num(route.style.thickness())
+        )
+        .unwrap();
+        if route.style.dashed() {
+            write!(
+                self.out,
+                r#" stroke-dasharray="{} {}""#,
+                num(DASH),
+                num(DASH_GAP)
+            )
+            .unwrap();
+        }
+        writeln!(self.out, "/>").unwrap();
+
+        if let Some(dir) = route.arrow {
+            let tip = *points.last().unwrap();
+            let size = ARROW_SIZE * self.zoom.max(0.3);
+            let side = vec2(-dir.y, dir.x) * size / 2.0;
+            let back = tip - dir * size;
+            let corners: Vec<_> = [tip, back + side, back - side]
+                .iter()
+                .map(|point| format!("{},{}", num(point.x), num(point.y)))
+                .collect();
+            writeln!(
+                self.out,
+                r#"<polygon points="{}" {}/>"#,
+                corners.join(" "),
+                paint("fill", color)
+            )
+            .unwrap();
+        }
+
+        let font_size = LABEL_FONT_SIZE * self.zoom;
+        if font_size >= 6.0 {
+            let pos = self.to_screen(route.label_pos);
+            self.text(&route.label, pos, font_size, color, "start");
+        }
+    }
+
+    /// `rect` is already in image coordinates.
+    fn toggle(&mut self, rect: Rect, collapsed: bool, hidden: usize) {
+        self.rect(rect, WHITE, Some((DARKGRAY, 2.0)));
+        let symbol = if collapsed { "+" } else { "-" };
+        let center = rect.center();
+        let pos = vec2(center.x, center.y + rect.h * 0.35);
+        self.text(symbol, pos, rect.h, BLACK, "middle");
+
+        if collapsed && hidden > 0 {
+            let badge = format!("+{} nodes", hidden);
+            let font_size = (rect.h * 0.8).max(14.0);
+            let width = text_width(&badge, font_size);
+            let x = rect.x - width - 10.0;
+            let background = Rect::new(x - 4.0, rect.y, width + 8.0, rect.h);
+            self.rect(background, YELLOW, None);
+            let pos = vec2(x, center.y + font_size * 0.35);
+            self.text(&badge, pos, font_size, BLACK, "start");
+        }
+    }
+
+    /// In the bottom left corner, under the graph.
+    fn legend(&mut self, title: &str, legend: &[(&str, Color)], image_height: f32) {
+        let width = legend
+            .iter()
+            .map(|(label, _)| text_width(label, LEGEND_FONT_SIZE) + LEGEND_SWATCH + LEGEND_PADDING)
+            .fold(text_width(title, LEGEND_FONT_SIZE), f32::max)
+            + 2.0 * LEGEND_PADDING;
+        let height = legend_height(legend);
+        let x = LEGEND_PADDING;
+        let y = image_height - height - LEGEND_PADDING;
+
+        self.rect(
+            Rect::new(x, y, width, height),
+            Color::new(1.0, 1.0, 1.0, 0.9),
+            Some((DARKGRAY, 2.0)),
+        );
+
+        let mut line_y = y + LEGEND_PADDING + LEGEND_FONT_SIZE * 0.8;
+        let text_x = x + LEGEND_PADDING;
+        self.text(
+            title,
+            vec2(text_x, line_y),
+            LEGEND_FONT_SIZE,
+            BLACK,
+            "start",
+        );
+        for (label, color) in legend {
+            line_y += LEGEND_FONT_SIZE;
+            let swatch = Rect::new(
+                text_x,
+                line_y - LEGEND_SWATCH + 2.0,
+                LEGEND_SWATCH,
+                LEGEND_SWATCH,
+            );
+            self.rect(swatch, *color, None);
+            let pos = vec2(text_x + LEGEND_PADDING + LEGEND_SWATCH, line_y);
+            self.text(label, pos, LEGEND_FONT_SIZE, BLACK, "start");
+        }
+    }
+}
+
+fn text_width(text: &str, font_size: f32) -> f32 {
+    text.chars().count() as f32 * font_size * CHAR_WIDTH
+}
+
+/// One decimal is plenty and keeps the output stable.
+fn num(x: f32) -> String {
+    let rounded = format!("{:.1}", x);
+    // Avoid "-0.0" which would make otherwise identical output differ.
+    match rounded.as_str() {
+        "-0.0" => "0.0".to_owned(),
+        _ => rounded,
+    }
+}
+
+/// `fill="#rrggbb"` plus the opacity if it's not opaque.
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, a]: [u8; 4] = color.into();
+    let mut paint = format!(r##"{}="#{:02x}{:02x}{:02x}""##, attribute, r, g, b);
+    if a < 255 {
+        write!(
+            paint,
+            r#" {}-opacity="{}""#,
+            attribute,
+            num(f32::from(a) / 255.0)
+        )
+        .unwrap();
+    }
+    paint
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use commonr::{data::Sexp, json};
+
+    use super::*;
+
+    /// The JSON golden document, so every payload kind gets a box.
+    fn update() -> Update {
+        json::from_json(include_str!("../../commonr/testdata/update-v1.json")).unwrap()
+    }
+
+    fn view_state() -> ViewState {
+        ViewState {
+            collapsed: Some([Sexp(0x500)].into_iter().collect()),
+            positions: [(Sexp(0xd00), [-400.0, 600.0])].into_iter().collect(),
+            annotations: [(Sexp(0x100), "f <- \"x\" & y".to_owned())]
+                .into_iter()
+                .collect(),
+            ..ViewState::default()
+        }
+    }
+
+    #[test]
+    fn escape_markup() {
+        assert_eq!(escape("plain text"), "plain text");
+        assert_eq!(
+            escape(r#"<a href="x">1 & 2</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;1 &amp; 2&lt;/a&gt;"
+        );
+        // Already escaped text is escaped again, it's shown as is.
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+        assert_eq!(escape("'é'"), "'é'");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(num(0.0), "0.0");
+        assert_eq!(num(12.34), "12.3");
+        assert_eq!(num(-7.25), "-7.2");
+        assert_eq!(num(2.0 / 3.0), "0.7");
+        assert_eq!(num(-0.0), "0.0");
+        assert_eq!(num(-0.04), "0.0");
+        assert_eq!(num(-0.05), "-0.1");
+    }
+
+    #[test]
+    fn paints() {
+        assert_eq!(paint("fill", WHITE), r##"fill="#ffffff""##);
+        assert_eq!(paint("stroke", DARKGRAY), r##"stroke="#4f4f4f""##);
+        assert_eq!(
+            paint("fill", Color::new(1.0, 0.0, 0.5, 0.5)),
+            r##"fill="#ff007f" fill-opacity="0.5""##
+        );
+        assert_eq!(
+            paint("stroke", Color::new(0.0, 0.0, 0.0, 0.0)),
+            r##"stroke="#000000" stroke-opacity="0.0""##
+        );
+    }
+
+    #[test]
+    fn golden() {
+        let image = render(&update(), &view_state(), &SvgOptions::default());
+        assert_eq!(image, include_str!("../testdata/update-v1.svg"));
+    }
+}
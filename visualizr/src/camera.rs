@@ -0,0 +1,101 @@
+use macroquad::prelude::*;
+use renderr::geometry;
+
+const MIN_ZOOM: f32 = 0.02;
+const MAX_ZOOM: f32 = 4.0;
+/// How much one notch of the mouse wheel zooms.
+const ZOOM_STEP: f32 = 1.15;
+/// Screen pixels per second when panning with the keyboard.
+const PAN_SPEED: f32 = 1000.0;
+/// Screen pixels around the graph when fitting it to the window.
+const FIT_MARGIN: f32 = 50.0;
+
+/// Maps world coordinates (where the layout puts boxes) to the screen.
+///
+/// Not using macroquad's Camera2D because the UI (editboxes) ignores it,
+/// we have to scale everything ourselves anyway.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Screen position of the world origin.
+    pub offset: Vec2,
+    /// Screen pixels per world unit.
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    #[must_use]
+    pub fn world_to_screen(&self, pos: Vec2) -> Vec2 {
+        pos * self.zoom + self.offset
+    }
+
+    #[must_use]
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        (pos - self.offset) / self.zoom
+    }
+
+    /// Move the view by the given amount of screen pixels.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset += delta;
+    }
+
+    /// Zoom by `factor` keeping the point under `screen_pos` in place.
+    pub fn zoom_at(&mut self, screen_pos: Vec2, factor: f32) {
+        let world_pos = self.screen_to_world(screen_pos);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = screen_pos - world_pos * self.zoom;
+    }
+
+    /// Show the whole rectangle (in world coordinates), centered.
+    ///
+    /// Doesn't zoom in past 1:1 - a single node filling the whole window isn't useful.
+    pub fn fit(&mut self, bounds: geometry::Rect) {
+        let screen = vec2(screen_width(), screen_height()) - Vec2::splat(2.0 * FIT_MARGIN);
+        let zoom = (screen.x / bounds.w).min(screen.y / bounds.h);
+        self.zoom = zoom.clamp(MIN_ZOOM, 1.0);
+        let center = vec2(bounds.x + bounds.w / 2.0, bounds.y + bounds.h / 2.0);
+        self.offset = vec2(screen_width(), screen_height()) / 2.0 - center * self.zoom;
+    }
+
+    /// Put the world position in the middle of the screen, keeping the zoom.
+    pub fn center_on(&mut self, pos: Vec2) {
+        self.offset = vec2(screen_width(), screen_height()) / 2.0 - pos * self.zoom;
+    }
+
+    /// Zooming with the mouse wheel and panning with arrow keys.
+    pub fn handle_input(&mut self) {
+        let (_, wheel) = mouse_wheel();
+        // The amount per notch differs between platforms, only the direction is reliable.
+        if wheel != 0.0 {
+            let factor = if wheel > 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            self.zoom_at(mouse_position().into(), factor);
+        }
+
+        let mut dir = Vec2::ZERO;
+        if is_key_down(KeyCode::Left) {
+            dir.x += 1.0;
+        }
+        if is_key_down(KeyCode::Right) {
+            dir.x -= 1.0;
+        }
+        if is_key_down(KeyCode::Up) {
+            dir.y += 1.0;
+        }
+        if is_key_down(KeyCode::Down) {
+            dir.y -= 1.0;
+        }
+        self.pan(dir * PAN_SPEED * get_frame_time());
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use renderr::args::{self, value};
+
+const USAGE: &str = "\
+usage: visualizr [options] [snapshot files...]
+
+Snapshot files (saved by visualize_save in R or Ctrl+S in visualizr)
+and JSON files (.json, exported by Ctrl+E) are opened in the order given,
+the view of the last snapshot file is restored.
+
+options:
+    --history <n>    how many received updates to keep (default 20)
+    -h, --help       print this help";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    /// How many snapshots to keep, at least 1.
+    pub history: usize,
+    /// Snapshot files to open.
+    pub files: Vec<PathBuf>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            history: 20,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl Args {
+    /// Parse the command line, print usage and exit on errors.
+    #[must_use]
+    pub fn parse() -> Self {
+        args::or_exit(Self::parse_from(std::env::args().skip(1)), USAGE)
+    }
+
+    /// Returns None if help was requested.
+    fn parse_from(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--history" => {
+                    parsed.history = value(&arg, args.next())?;
+                    if parsed.history == 0 {
+                        return Err("--history must be at least 1".to_owned());
+                    }
+                }
+                "-h" | "--help" => return Ok(None),
+                _ if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+                _ => parsed.files.push(arg.into()),
+            }
+        }
+        Ok(Some(parsed))
+    }
+}
@@ -0,0 +1,55 @@
+use macroquad::prelude::*;
+use renderr::{
+    colors::{legend_height, LEGEND_FONT_SIZE, LEGEND_PADDING, LEGEND_SWATCH},
+    geometry,
+};
+
+/// renderr has its own colors so it doesn't depend on macroquad.
+#[must_use]
+pub fn to_macroquad(color: geometry::Color) -> Color {
+    Color::from(<[f32; 4]>::from(color))
+}
+
+/// Explain the current colors in the bottom left corner of the screen.
+///
+/// LATER Editboxes are UI so they're drawn over this when zoomed in.
+pub fn draw_legend(title: &str, legend: &[(&str, geometry::Color)]) {
+    let width = legend
+        .iter()
+        .map(|(label, _)| {
+            measure_text(label, None, LEGEND_FONT_SIZE as u16, 1.0).width
+                + LEGEND_SWATCH
+                + LEGEND_PADDING
+        })
+        .fold(
+            measure_text(title, None, LEGEND_FONT_SIZE as u16, 1.0).width,
+            f32::max,
+        )
+        + 2.0 * LEGEND_PADDING;
+    let height = legend_height(legend);
+    let x = LEGEND_PADDING;
+    let y = screen_height() - height - LEGEND_PADDING;
+
+    draw_rectangle(x, y, width, height, Color::new(1.0, 1.0, 1.0, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, DARKGRAY);
+
+    let mut line_y = y + LEGEND_PADDING + LEGEND_FONT_SIZE * 0.8;
+    draw_text(title, x + LEGEND_PADDING, line_y, LEGEND_FONT_SIZE, BLACK);
+    for (label, color) in legend {
+        line_y += LEGEND_FONT_SIZE;
+        draw_rectangle(
+            x + LEGEND_PADDING,
+            line_y - LEGEND_SWATCH + 2.0,
+            LEGEND_SWATCH,
+            LEGEND_SWATCH,
+            to_macroquad(*color),
+        );
+        draw_text(
+            label,
+            x + 2.0 * LEGEND_PADDING + LEGEND_SWATCH,
+            line_y,
+            LEGEND_FONT_SIZE,
+            BLACK,
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use macroquad::prelude::*;
+use renderr::edges::{Route, ARROW_SIZE, DASH, DASH_GAP, LABEL_FONT_SIZE};
+
+use crate::{camera::Camera, colors::to_macroquad};
+
+/// Draw the route in screen coordinates.
+pub fn draw_route(route: &Route, camera: &Camera) {
+    let color = to_macroquad(route.style.color());
+    let thickness = route.style.thickness();
+    let points: Vec<_> = route
+        .points
+        .iter()
+        .map(|&point| camera.world_to_screen(point))
+        .collect();
+    for segment in points.windows(2) {
+        let a = segment[0];
+        let b = segment[1];
+        if route.style.dashed() {
+            draw_dashed_line(a, b, thickness, color);
+        } else {
+            draw_line(a.x, a.y, b.x, b.y, thickness, color);
+        }
+    }
+
+    // Arrowheads and labels scale with the zoom, lines stay thin enough to see.
+    if let Some(dir) = route.arrow {
+        let tip = *points.last().unwrap();
+        let size = ARROW_SIZE * camera.zoom.max(0.3);
+        let side = vec2(-dir.y, dir.x) * size / 2.0;
+        let back = tip - dir * size;
+        draw_triangle(tip, back + side, back - side, color);
+    }
+
+    let font_size = LABEL_FONT_SIZE * camera.zoom;
+    // Unreadable anyway and it's a lot of text to draw.
+    if font_size >= 6.0 {
+        let label_pos = camera.world_to_screen(route.label_pos);
+        draw_text(&route.label, label_pos.x, label_pos.y, font_size, color);
+    }
+}
+
+fn draw_dashed_line(a: Vec2, b: Vec2, thickness: f32, color: Color) {
+    let length = (b - a).length();
+    if length == 0.0 {
+        return;
+    }
+    let dir = (b - a) / length;
+    let mut start = 0.0;
+    while start < length {
+        let end = (start + DASH).min(length);
+        let p = a + dir * start;
+        let q = a + dir * end;
+        draw_line(p.x, p.y, q.x, q.y, thickness, color);
+        start += DASH + DASH_GAP;
+    }
+}
@@ -0,0 +1,249 @@
+use commonr::{
+    data::{truncate, Sexp, SexpFormatter, Sexprec},
+    diff::{Diff, Status},
+    gp::GpFlags,
+    graph::{ObjectGraph, RefCount},
+    semantic::describe,
+};
+use macroquad::{
+    hash, miniquad,
+    prelude::*,
+    ui::{root_ui, widgets::Window, Ui},
+    window::get_internal_gl,
+};
+
+const WIDTH: f32 = 520.0;
+const MARGIN: f32 = 10.0;
+
+/// What the user clicked in the inspector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectorAction {
+    /// Select the node and move the camera to it.
+    Jump(Sexp),
+    /// Open the note editor for the node.
+    EditNote,
+    Close,
+}
+
+/// Where the panel is on screen so clicks there don't go through to the graph.
+#[must_use]
+pub fn inspector_rect() -> Rect {
+    Rect::new(
+        screen_width() - WIDTH - MARGIN,
+        MARGIN,
+        WIDTH,
+        screen_height() - 2.0 * MARGIN,
+    )
+}
+
+/// Side panel with everything we know about the selected node.
+///
+/// Replaces copying from the editboxes in the nodes - those get reset every frame
+/// so selections get lost. Here every address has a copy button instead.
+///
+/// In the `semantic` view, it starts with the semantic description,
+/// each entry links to the raw node it came from.
+/// With a `diff`, it also lists how the node differs from the base snapshot.
+pub fn draw_inspector(
+    graph: &ObjectGraph,
+    sexp: Sexp,
+    semantic: bool,
+    diff: Option<&Diff>,
+    note: Option<&str>,
+) -> Option<InspectorAction> {
+    let sexprec = graph.get(sexp)?;
+    let globals = graph.globals();
+    let rect = inspector_rect();
+    let mut action = None;
+
+    Window::new(hash!(), rect.point(), rect.size())
+        .label("inspector")
+        .movable(false)
+        .ui(&mut root_ui(), |ui| {
+            if ui.button(None, "close") {
+                action = Some(InspectorAction::Close);
+            }
+            ui.separator();
+
+            ui.label(None, &format!("{} ({})", sexprec.ty, sexprec.ty_name));
+            address_line(ui, &globals.fmt_ptr(sexp), sexp);
+            for root in graph.roots() {
+                if root.sexp == sexp {
+                    ui.label(None, &format!("root: {}", root.name));
+                }
+            }
+            if let Some(name) = graph.symbol_name(sexp) {
+                ui.label(None, &format!("symbol: {}", name));
+            }
+            if let Some(string) = graph.string_value(sexp) {
+                ui.label(None, &format!("string: {:?}", truncate(string, 50)));
+                if ui.button(None, "copy string") {
+                    copy(string);
+                }
+            }
+            if let Some(note) = note {
+                ui.label(None, &format!("note: {}", note));
+            }
+            if ui.button(None, "edit note") {
+                action = Some(InspectorAction::EditNote);
+            }
+            ui.separator();
+
+            if let Some(diff) = diff {
+                diff_lines(ui, diff, sexp);
+                ui.separator();
+            }
+
+            if semantic {
+                if let Some(description) = describe(graph, sexp) {
+                    ui.label(None, &description.header);
+                    for entry in &description.entries {
+                        let text = format!("{}: {}", entry.label, entry.value);
+                        if ui.button(None, text.as_str()) {
+                            action = Some(InspectorAction::Jump(entry.sexp));
+                        }
+                    }
+                    ui.separator();
+                }
+            }
+
+            sxpinfo_lines(ui, sexprec);
+            ui.separator();
+
+            ui.label(None, "outgoing:");
+            let mut ptrs = vec![("attrib", sexprec.attrib)];
+            ptrs.extend(sexprec.payload.pointers());
+            for (field, dest) in ptrs {
+                if let Some(jump) = reference(ui, graph, field, dest) {
+                    action = Some(jump);
+                }
+            }
+            ui.separator();
+
+            let incoming = graph.incoming(sexp);
+            ui.label(None, &format!("incoming ({}):", incoming.len()));
+            match graph.ref_count(sexp) {
+                Some(RefCount::Matches) | None => {}
+                Some(RefCount::Outside(n)) => {
+                    ui.label(
+                        None,
+                        &format!(
+                            "named is {} higher - references from outside the update?",
+                            n
+                        ),
+                    );
+                }
+                Some(RefCount::Untracked(n)) => {
+                    ui.label(
+                        None,
+                        &format!("named is {} lower - untracked references or a bug?", n),
+                    );
+                }
+            }
+            for edge in incoming {
+                let field = format!("{} of", edge.name);
+                if let Some(jump) = reference(ui, graph, &field, edge.src) {
+                    action = Some(jump);
+                }
+            }
+            ui.separator();
+
+            if ui.button(None, "copy full dump") {
+                copy(&SexpFormatter(globals, sexprec).to_string());
+            }
+        });
+
+    action
+}
+
+fn address_line(ui: &mut Ui, text: &str, sexp: Sexp) {
+    ui.label(None, &format!("address: {}", text));
+    copy_button(ui, sexp);
+}
+
+/// Copies the address in the format gdb and friends understand.
+fn copy_button(ui: &mut Ui, sexp: Sexp) {
+    ui.same_line(WIDTH - 70.0);
+    if ui.button(None, "copy") {
+        copy(&format!("{:#x}", sexp.0));
+    }
+}
+
+fn diff_lines(ui: &mut Ui, diff: &Diff, sexp: Sexp) {
+    let status = match diff.statuses.get(&sexp) {
+        Some(Status::Same) => "same".to_owned(),
+        Some(Status::Changed(changes)) => format!("{} changes", changes.len()),
+        Some(Status::Added) => "added".to_owned(),
+        Some(Status::Removed) => "removed".to_owned(),
+        None => "unknown".to_owned(),
+    };
+    ui.label(None, &format!("diff {}: {}", diff.matching.name(), status));
+    if let Some(&old) = diff.old_addresses.get(&sexp) {
+        if old != sexp {
+            address_line(ui, &format!("{:#x} in base", old.0), old);
+        }
+    }
+    if let Some(Status::Changed(changes)) = diff.statuses.get(&sexp) {
+        for change in changes {
+            ui.label(
+                None,
+                &format!("{}: {} -> {}", change.field, change.old, change.new),
+            );
+        }
+    }
+}
+
+fn sxpinfo_lines(ui: &mut Ui, sexprec: &Sexprec) {
+    let info = &sexprec.sxpinfo;
+    ui.label(
+        None,
+        &format!(
+            "named: {}  obj: {}  scalar: {}  alt: {}",
+            info.named, info.obj, info.scalar, info.alt
+        ),
+    );
+    ui.label(
+        None,
+        &format!(
+            "gcgen: {}  gccls: {}  mark: {}  debug: {}  trace: {}",
+            info.gcgen, info.gccls, info.mark, info.debug, info.trace
+        ),
+    );
+    // The u16, the i32 is sign-extended when bit 15 is set (e.g. GLOBAL_FRAME).
+    let flags = GpFlags::new(sexprec.ty, info.gp);
+    ui.label(None, &format!("gp: {:#018b}", flags.gp));
+    ui.label(None, &format!("gp flags: {}", flags));
+}
+
+/// A pointer to another node - click to jump there.
+fn reference(
+    ui: &mut Ui,
+    graph: &ObjectGraph,
+    field: &str,
+    other: Sexp,
+) -> Option<InspectorAction> {
+    let address = graph.globals().fmt_ptr(other);
+    let mut action = None;
+    match graph.get(other) {
+        Some(other_rec) => {
+            let mut text = format!("{}: {} {}", field, other_rec.ty, address);
+            if let Some(name) = graph.short_name(other) {
+                text.push_str(&format!(" {}", name));
+            }
+            if ui.button(None, text.as_str()) {
+                action = Some(InspectorAction::Jump(other));
+            }
+        }
+        None => {
+            ui.label(None, &format!("{}: {} (not sent)", field, address));
+        }
+    }
+    copy_button(ui, other);
+    action
+}
+
+fn copy(text: &str) {
+    // Macroquad only exposes the clipboard through editboxes.
+    let gl = unsafe { get_internal_gl() };
+    miniquad::clipboard::set(gl.quad_context, text);
+}
@@ -1,14 +1,57 @@
+mod camera;
+mod cli;
+mod colors;
+mod edges;
+mod inspector;
+mod nodes;
+mod note;
+mod search;
+mod timeline;
+
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
     io::ErrorKind,
     net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
 };
 
-use commonr::{data::*, net};
+use commonr::{
+    data::*,
+    diff::{Diff, Matching, Status},
+    dot::{self, DotOptions},
+    graph::ObjectGraph,
+    json, net,
+    semantic::boxed,
+    snapshot::{self, CameraState, SnapshotFile, ViewState},
+};
 use macroquad::{
     hash,
     prelude::*,
-    ui::{root_ui, widgets::Group},
+    ui::{root_ui, widgets::Group, Skin},
+};
+use renderr::{
+    colors::{diff_color, pale, ColorScheme, DIFF_LEGEND},
+    edges::{route_edges, Route},
+    force::{pinned, ForceLayout},
+    history::{History, Snapshot},
+    layout::{bounds, collapsible, default_collapsed, Layout, TidyTree, BOX_HEIGHT, BOX_WIDTH},
+    load_file,
+    nodes::{
+        frame_color, label_font_size, node_labels, node_text, title, toggle_rect, Detail,
+        FULL_FONT_SIZE,
+    },
+};
+
+use crate::{
+    camera::Camera,
+    cli::Args,
+    colors::{draw_legend, to_macroquad},
+    edges::draw_route,
+    inspector::{draw_inspector, inspector_rect, InspectorAction},
+    nodes::{draw_chip, draw_ref_count, draw_summary, draw_toggle, on_screen},
+    note::NoteEditor,
+    search::SearchBar,
 };
 
 // TODO RA doesn't work on this file???
@@ -69,54 +112,313 @@ struct Connection {
 
 #[derive(Debug)]
 struct State {
-    globals: Globals,
-    sexprecs: Vec<Sexprec>,
-    nodes: HashMap<String, Node>,
-    offset: Vec2,
+    graph: ObjectGraph,
+    /// The graph shown in the semantic view - only nodes with their own box, see `semantic::boxed`.
+    /// None in the raw view.
+    semantic: Option<ObjectGraph>,
+    /// When comparing to another snapshot, `graph` is the merged graph.
+    diff: Option<Diff>,
+    /// Non-fatal issues - we draw what we can and show these next to it.
+    report: ValidationReport,
+    /// Nodes reachable from multiple roots and the indices of those roots.
+    shared: HashMap<Sexp, Vec<usize>>,
+    /// Nodes whose children are hidden.
+    collapsed: HashSet<Sexp>,
+    /// Nodes hidden by collapsing, see `ObjectGraph::hidden`.
+    hidden: HashSet<Sexp>,
+    /// How many nodes each collapsed node hides.
+    badges: HashMap<Sexp, usize>,
+    /// Top left corners of the visible boxes.
+    positions: HashMap<Sexp, Vec2>,
+    /// Which layout computes `positions`.
+    layout: LayoutMode,
+    /// Only used in `LayoutMode::Force`.
+    force: ForceLayout,
+    /// Where the user dragged nodes, overrides the layout.
+    /// Kept across updates - if the same address shows up again, it goes to the same place.
+    manual: HashMap<Sexp, Vec2>,
+    /// Notes the user attached to nodes, kept across updates like `manual`.
+    annotations: HashMap<Sexp, String>,
+    /// Edge routes for the current positions, None if they need recomputing.
+    /// Routing is too slow to do every frame for large graphs.
+    routes: Option<Vec<Route>>,
+    selected: Option<Sexp>,
+    drag: Option<Drag>,
+    /// Where the left button went down, to tell clicks from drags.
+    press_pos: Vec2,
+    camera: Camera,
     prev_mouse_pos: Vec2,
 }
 
 impl State {
-    fn new(globals: Globals, sexprecs: Vec<Sexprec>, nodes: HashMap<String, Node>) -> Self {
+    fn new(
+        graph: ObjectGraph,
+        report: ValidationReport,
+        manual: HashMap<Sexp, Vec2>,
+        layout: LayoutMode,
+    ) -> Self {
         // This returns 0,0 until the mouse moves for the first time after opening the window
         // so the first drag can be glitchy and there's nothing i can do about it.
         let prev_mouse_pos = mouse_position().into();
-        Self {
-            globals,
-            sexprecs,
-            nodes,
-            offset: Vec2::ZERO,
+        let shared = graph.shared();
+        let collapsed = default_collapsed(&graph);
+        let mut state = Self {
+            graph,
+            semantic: None,
+            diff: None,
+            report,
+            shared,
+            collapsed,
+            hidden: HashSet::new(),
+            badges: HashMap::new(),
+            positions: HashMap::new(),
+            layout,
+            force: ForceLayout::default(),
+            manual,
+            annotations: HashMap::new(),
+            routes: None,
+            selected: None,
+            drag: None,
+            press_pos: Vec2::ZERO,
+            camera: Camera::default(),
             prev_mouse_pos,
+        };
+        state.update_hidden();
+        state.relayout();
+        if let Some(bounds) = bounds(&state.positions) {
+            state.camera.fit(bounds);
+        }
+        state
+    }
+
+    /// The graph which is drawn - the raw one or the semantic one.
+    fn view(&self) -> &ObjectGraph {
+        self.semantic.as_ref().unwrap_or(&self.graph)
+    }
+
+    fn set_semantic(&mut self, semantic: bool) {
+        self.semantic = semantic.then(|| self.graph.contracted(&boxed(&self.graph)));
+        self.update_hidden();
+        self.relayout();
+        if let Some(bounds) = bounds(&self.positions) {
+            self.camera.fit(bounds);
+        }
+    }
+
+    /// Recompute positions after the visible nodes changed, keeping nodes the user moved where they are.
+    ///
+    /// In the tree layout, everything is laid out again.
+    /// The force-directed layout continues from the current positions,
+    /// only nodes which weren't visible before start where the tree layout puts them.
+    fn relayout(&mut self) {
+        let tree = TidyTree::default().layout(self.view(), &self.hidden);
+        let old = std::mem::take(&mut self.positions);
+        self.positions = tree
+            .into_iter()
+            .map(|(sexp, pos)| {
+                let pos = match (self.layout, old.get(&sexp)) {
+                    (LayoutMode::Force, Some(&current)) => current,
+                    _ => pos,
+                };
+                (sexp, self.manual.get(&sexp).copied().unwrap_or(pos))
+            })
+            .collect();
+        self.force.reset();
+        self.routes = None;
+    }
+
+    /// Run the force-directed layout for at most `budget` seconds.
+    fn simulate(&mut self, budget: f64) {
+        if self.layout != LayoutMode::Force || self.force.is_settled() {
+            return;
+        }
+        let start = get_time();
+        let pinned = pinned(&self.graph, &self.manual);
+        let view = self.semantic.as_ref().unwrap_or(&self.graph);
+        while get_time() - start < budget {
+            if !self.force.step(view, &mut self.positions, &pinned) {
+                break;
+            }
+        }
+        // Only once per frame - routing is the slow part.
+        self.routes = None;
+    }
+
+    fn update_hidden(&mut self) {
+        let view = self.view();
+        let hidden = view.hidden(&self.collapsed);
+        self.badges = self
+            .collapsed
+            .iter()
+            .map(|&sexp| (sexp, view.hidden_under(sexp, &hidden)))
+            .collect();
+        self.hidden = hidden;
+    }
+
+    fn collapsible(&self, sexp: Sexp) -> bool {
+        collapsible(self.view(), &self.collapsed, sexp)
+    }
+
+    fn toggle_collapsed(&mut self, sexp: Sexp) {
+        if !self.collapsed.remove(&sexp) {
+            self.collapsed.insert(sexp);
+        }
+        self.update_hidden();
+        self.relayout();
+    }
+
+    /// Expand collapsed nodes until `sexp` is visible.
+    fn reveal(&mut self, sexp: Sexp) {
+        while self.hidden.contains(&sexp) {
+            let expand = self
+                .collapsed
+                .iter()
+                .copied()
+                .find(|&c| !self.hidden.contains(&c) && self.view().reaches(c, sexp));
+            match expand {
+                Some(c) => {
+                    self.collapsed.remove(&c);
+                    self.update_hidden();
+                }
+                None => break,
+            }
+        }
+        self.relayout();
+    }
+
+    /// Select the node and center the camera on it.
+    fn jump_to(&mut self, sexp: Sexp) {
+        if !self.graph.contains(sexp) {
+            return;
+        }
+        // Links from the semantic view lead to the raw nodes.
+        if !self.view().contains(sexp) {
+            self.set_semantic(false);
+        }
+        if self.hidden.contains(&sexp) {
+            self.reveal(sexp);
+        }
+        self.selected = Some(sexp);
+        if let Some(pos) = self.positions.get(&sexp) {
+            self.camera
+                .center_on(*pos + vec2(BOX_WIDTH, BOX_HEIGHT) / 2.0);
+        }
+    }
+
+    /// What goes into a snapshot file along with the update.
+    fn view_state(&self) -> ViewState {
+        let center = self
+            .camera
+            .screen_to_world(vec2(screen_width(), screen_height()) / 2.0);
+        ViewState {
+            camera: Some(CameraState {
+                center: [center.x, center.y],
+                zoom: self.camera.zoom,
+            }),
+            semantic: self.semantic.is_some(),
+            collapsed: Some(self.collapsed.clone()),
+            positions: self
+                .positions
+                .iter()
+                .map(|(&sexp, pos)| (sexp, [pos.x, pos.y]))
+                .collect(),
+            annotations: self.annotations.clone(),
+        }
+    }
+
+    /// Restore the view from a snapshot file.
+    ///
+    /// Saved positions are treated as if the user moved the nodes there,
+    /// otherwise the force-directed layout would immediately move them.
+    fn apply_view(&mut self, view: ViewState) {
+        self.manual.extend(
+            view.positions
+                .into_iter()
+                .map(|(sexp, [x, y])| (sexp, vec2(x, y))),
+        );
+        self.annotations.extend(view.annotations);
+        if let Some(collapsed) = view.collapsed {
+            self.collapsed = collapsed;
+        }
+        // Also recomputes what's hidden and the layout.
+        self.set_semantic(view.semantic);
+        if let Some(camera) = view.camera {
+            self.camera.zoom = camera.zoom;
+            self.camera.center_on(camera.center.into());
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct Node {
-    pos: Vec2,
-    links: Vec<Link>,
+/// What carries over from one snapshot to the next.
+#[derive(Debug, Default)]
+struct Kept {
+    manual: HashMap<Sexp, Vec2>,
+    annotations: HashMap<Sexp, String>,
+    semantic: bool,
+    layout: LayoutMode,
+    camera: Option<Camera>,
 }
 
-impl Node {
-    fn new() -> Self {
-        Self {
-            pos: Vec2::ZERO,
-            links: Vec::new(),
+impl State {
+    fn kept(self) -> Kept {
+        Kept {
+            manual: self.manual,
+            annotations: self.annotations,
+            semantic: self.semantic.is_some(),
+            layout: self.layout,
+            camera: Some(self.camera),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct Link {
-    link_type: LinkType,
-    dest_addr: String,
-    dest_global: bool,
+/// `keep_camera` is for switching between snapshots,
+/// new ones are fitted into the window instead.
+///
+/// With a `base`, the snapshot is compared to it and the merged graph is shown.
+fn show(
+    snapshot: &Snapshot,
+    base: Option<&Snapshot>,
+    matching: Matching,
+    kept: &Kept,
+    keep_camera: bool,
+) -> State {
+    let diff = base.map(|base| Diff::new(&base.update, &snapshot.update, matching));
+    let update = match &diff {
+        Some(diff) => diff.merged.clone(),
+        None => snapshot.update.clone(),
+    };
+    let mut state = State::new(
+        ObjectGraph::new(update),
+        snapshot.report.clone(),
+        kept.manual.clone(),
+        kept.layout,
+    );
+    state.diff = diff;
+    state.annotations = kept.annotations.clone();
+    if kept.semantic {
+        state.set_semantic(true);
+    }
+    if let (true, Some(camera)) = (keep_camera, kept.camera) {
+        state.camera = camera;
+    }
+    state
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum LinkType {
-    Attrib,
-    Payload(usize),
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum LayoutMode {
+    #[default]
+    Tree,
+    Force,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    Pan,
+    /// Moving a node, grab is the mouse position relative to the node in world coordinates.
+    Node {
+        sexp: Sexp,
+        grab: Vec2,
+    },
 }
 
 fn window_conf() -> Conf {
@@ -136,220 +438,634 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
+    let args = Args::parse();
     let mut server = Server::new();
-    let mut state = None;
+    let mut history = History::new(args.history);
+    let mut scheme = ColorScheme::Type;
+    let mut state: Option<State> = None;
+    let mut rejected = None;
+    let mut skins = Skins::default();
+    let mut search = SearchBar::new();
+    let mut note = NoteEditor::new();
+    // Shown at the top for a few seconds, e.g. where a snapshot was saved.
+    let mut message: Option<(String, f64)> = None;
+    let mut kept = Kept::default();
+    let mut matching = Matching::Address;
+    // The user picked another snapshot from the history.
+    let mut switched = false;
+
+    // Only from the command line, the pinned miniquad has no file drop events.
+    let mut opened = None;
+    for path in &args.files {
+        match open_file(path, &mut history) {
+            Ok(view) => opened = Some(view),
+            Err(err) => {
+                eprintln!("{}", err);
+                rejected = Some(err);
+            }
+        }
+    }
+    if let Some(view) = opened {
+        state = history.current().map(|snapshot| {
+            let mut state = show(snapshot, None, matching, &kept, false);
+            state.apply_view(view);
+            state
+        });
+    }
+
     loop {
         server.receive();
 
         for update in server.msgs.drain(..) {
-            let new_state = handle_update(update);
-            state = Some(new_state);
+            // Even if the update is rejected, keep the positions for the next one.
+            if let Some(old) = state.take() {
+                kept = old.kept();
+            }
+            match handle_update(update, &mut history) {
+                Ok(()) => {
+                    state = history
+                        .current()
+                        .map(|snapshot| show(snapshot, history.base(), matching, &kept, false));
+                    search.invalidate();
+                    rejected = None;
+                }
+                Err(report) => {
+                    rejected = Some(report);
+                }
+            }
+        }
+
+        // Typing into the search bar or a note shouldn't trigger shortcuts.
+        let typing = search.open || note.open;
+
+        // Not gated on state, after a rejected update there's none but the user can go back.
+        if history.current().is_some() && !typing {
+            switched |= timeline::handle_keys(&mut history);
+            if is_key_pressed(KeyCode::D) {
+                history.toggle_base();
+                switched = true;
+            }
+            if is_key_pressed(KeyCode::M) && history.base().is_some() {
+                matching = match matching {
+                    Matching::Address => Matching::Structure,
+                    Matching::Structure => Matching::Address,
+                };
+                switched = true;
+            }
+        }
+        if switched {
+            if let Some(old) = state.take() {
+                kept = old.kept();
+            }
+            state = history
+                .current()
+                .map(|snapshot| show(snapshot, history.base(), matching, &kept, true));
+            search.invalidate();
+            rejected = None;
+        }
+
+        if state.is_some() && !note.open {
+            search.handle_keys();
         }
+        note.handle_keys();
+        let shortcuts = !search.open && !note.open;
 
         if let Some(state) = &mut state {
-            let cur_mouse_pos = mouse_position().into();
-            if is_mouse_button_down(MouseButton::Left) {
-                // Would be nice to grab mouse here and teleport it to the other side
-                // if it hits an edge, like in blender.
-                // Unfortunately, set_cursor_grab changes sensitivity
-                // and i don't see any way to change mouse position anyway.
-                state.offset += cur_mouse_pos - state.prev_mouse_pos;
+            let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+            // Ctrl+C copies from the editboxes.
+            if shortcuts && !ctrl && is_key_pressed(KeyCode::C) {
+                scheme = scheme.next();
+            }
+            if shortcuts && is_key_pressed(KeyCode::L) {
+                state.layout = match state.layout {
+                    LayoutMode::Tree => LayoutMode::Force,
+                    LayoutMode::Force => LayoutMode::Tree,
+                };
+                state.relayout();
+            }
+            if shortcuts && is_key_pressed(KeyCode::V) {
+                state.set_semantic(state.semantic.is_none());
+            }
+            if shortcuts && is_key_pressed(KeyCode::R) {
+                // Forget where the user put things, also restarts the force-directed layout from the tree.
+                state.manual.clear();
+                state.positions.clear();
+                state.relayout();
+            }
+
+            if shortcuts && is_key_pressed(KeyCode::F) {
+                if let Some(bounds) = bounds(&state.positions) {
+                    state.camera.fit(bounds);
+                }
+            }
+            if shortcuts && is_key_pressed(KeyCode::Key0) {
+                state.camera = Camera::default();
+            }
+            let saved = if shortcuts && ctrl && is_key_pressed(KeyCode::S) {
+                history.current().map(|snapshot| save_file(state, snapshot))
+            } else if shortcuts && ctrl && is_key_pressed(KeyCode::E) {
+                history.current().map(export_json)
+            } else if shortcuts && ctrl && is_key_pressed(KeyCode::G) {
+                history.current().map(export_dot)
+            } else {
+                None
+            };
+            if let Some(saved) = saved {
+                let text = match saved {
+                    Ok(path) => format!("saved to {}", path.display()),
+                    Err(err) => err,
+                };
+                println!("{}", text);
+                message = Some((text, get_time() + MESSAGE_DURATION));
             }
-            state.prev_mouse_pos = cur_mouse_pos;
+            if shortcuts {
+                state.camera.handle_input();
+            }
+
+            let mouse = mouse_position().into();
+            let over_ui = search.contains(mouse)
+                || note.contains(mouse)
+                || timeline::contains(&history, mouse);
+            handle_mouse(state, over_ui);
+
+            // Simulate for a limited time each frame so the window stays responsive.
+            state.simulate(FORCE_BUDGET);
         }
 
         clear_background(WHITE);
 
         if let Some(state) = &mut state {
-            draw_tree(state);
+            draw_tree(state, &mut skins, scheme, &mut note);
+            match &state.diff {
+                Some(diff) => {
+                    let title = format!("diff {} (M to change, D to stop)", diff.matching.name());
+                    draw_legend(&title, DIFF_LEGEND);
+                }
+                None => {
+                    let title = format!("color: {} (C to change)", scheme.name());
+                    draw_legend(&title, scheme.legend());
+                }
+            }
+            if let Some(sexp) = search.draw(&state.graph) {
+                state.jump_to(sexp);
+            }
+            if let Some((sexp, text)) = note.draw() {
+                if text.is_empty() {
+                    state.annotations.remove(&sexp);
+                } else {
+                    state.annotations.insert(sexp, text);
+                }
+            }
+            // After drawing the editor, the N would be typed into it in the frame it opens.
+            if shortcuts && is_key_pressed(KeyCode::N) {
+                if let Some(selected) = state.selected {
+                    note.edit(selected, state.annotations.get(&selected));
+                }
+            }
+        } else if let Some(report) = &rejected {
+            draw_report_box(&mut skins, report);
         } else {
-            draw_initial_box();
+            draw_initial_box(&mut skins);
+        }
+        // Also after a rejected update so the user can go back.
+        switched = timeline::draw(&mut history);
+        if let Some((text, until)) = &message {
+            if get_time() < *until {
+                draw_message(text);
+            } else {
+                message = None;
+            }
         }
 
         next_frame().await
     }
 }
 
-fn handle_update(update: Update) -> State {
-    // Debug stuff
-    dbg!(&update);
-    let mut incoming_ptrs = HashMap::new();
-    for sexprec in &update.sexprecs {
-        let node_addr = update.globals.fmt_ptr(sexprec.address);
-        incoming_ptrs.entry(node_addr).or_insert(0);
-        let mut ptrs = sexprec.payload.pointers();
-        ptrs.push(("", sexprec.attrib));
-        for (_name, ptr) in ptrs {
-            let dest_addr = update.globals.fmt_ptr(ptr);
-            let cnt = incoming_ptrs.entry(dest_addr).or_insert(0);
-            *cnt += 1;
-        }
-    }
-    dbg!(incoming_ptrs);
-
-    // Collect links between nodes
-    let mut nodes = HashMap::new();
-    for sexprec in &update.sexprecs {
-        let mut node = Node::new();
-
-        let attrib_global = update.globals.is_global(sexprec.attrib);
-        let attrib_addr = update.globals.fmt_ptr(sexprec.attrib);
-        node.links.push(Link {
-            link_type: LinkType::Attrib,
-            dest_addr: attrib_addr,
-            dest_global: attrib_global,
-        });
-        let ptrs = sexprec.payload.pointers();
-        for i in 0..ptrs.len() {
-            let (_name, ptr) = ptrs[i];
-            let dest_global = update.globals.is_global(ptr);
-            let dest_addr = update.globals.fmt_ptr(ptr);
-            node.links.push(Link {
-                link_type: LinkType::Payload(i),
-                dest_addr,
-                dest_global,
-            });
-        }
-
-        let addr = update.globals.fmt_ptr(sexprec.address);
-        nodes.insert(addr, node);
-    }
-
-    // Layout
-    // We probably can't use a general DAG layout algo because
-    // we want the edges to originate from specific parts of the node
-    // and we don't want to reorder children.
-    // Just in case, this looks interesting: https://reposhub.com/javascript/data-visualization/erikbrinkman-d3-dag.html#examples
-    let root_addr = update.globals.fmt_ptr(update.sexprecs[0].address);
-    let margin = walk(&mut nodes, root_addr, vec2(BOX_INIT_X, BOX_INIT_Y));
-
-    // Put globals on the left.
-    // Originally i wanted to put them at the bottom but they were hard to find if the tree was large.
-    let mut global_pos = vec2(
-        BOX_INIT_X - BOX_WIDTH - 2.0 * BOX_GAP,
-        BOX_INIT_Y + BOX_HEIGHT + BOX_GAP,
+/// Add the update to the history or return the formatted validation report if it's too broken to draw.
+fn handle_update(update: Update, history: &mut History) -> Result<(), String> {
+    let report = update.validate();
+    if !report.is_ok() {
+        println!("{}", ReportFormatter(&update.globals, &report));
+    }
+    if report.is_fatal() {
+        return Err(ReportFormatter(&update.globals, &report).to_string());
+    }
+
+    history.push(update, report);
+    Ok(())
+}
+
+/// Load a snapshot or JSON file into the history, returns the view to restore.
+fn open_file(path: &Path, history: &mut History) -> Result<ViewState, String> {
+    let file = load_file(path)?;
+    handle_update(file.update, history)?;
+    Ok(file.view)
+}
+
+/// Where to save the snapshot in the current directory.
+fn file_name(snapshot: &Snapshot, extension: &str) -> PathBuf {
+    // Include our pid so snapshots from different runs don't overwrite each other.
+    PathBuf::from(format!(
+        "snapshot-{}-{}.{}",
+        std::process::id(),
+        snapshot.number,
+        extension
+    ))
+}
+
+/// Save the snapshot and how it's shown into the current directory.
+///
+/// When comparing snapshots, only the current one is saved, not the merged graph.
+fn save_file(state: &State, snapshot: &Snapshot) -> Result<PathBuf, String> {
+    let path = file_name(snapshot, snapshot::EXTENSION);
+    let file = SnapshotFile {
+        update: snapshot.update.clone(),
+        view: state.view_state(),
+    };
+    snapshot::save(&path, &file)
+        .map_err(|err| format!("failed to save {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Save just the update as JSON for other tools, see `commonr::json`.
+fn export_json(snapshot: &Snapshot) -> Result<PathBuf, String> {
+    let path = file_name(snapshot, json::EXTENSION);
+    json::save(&path, &snapshot.update)
+        .map_err(|err| format!("failed to save {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Save the update as a Graphviz graph with the default options.
+/// The `dot` subcommand has options for what to include.
+fn export_dot(snapshot: &Snapshot) -> Result<PathBuf, String> {
+    let path = file_name(snapshot, "dot");
+    let graph = dot::to_dot(&snapshot.update, &DotOptions::default());
+    fs::write(&path, graph).map_err(|err| format!("failed to save {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Seconds to show messages for.
+const MESSAGE_DURATION: f64 = 5.0;
+
+fn draw_message(text: &str) {
+    let font_size = 24.0;
+    let dims = measure_text(text, None, font_size as u16, 1.0);
+    let pos = vec2((screen_width() - dims.width) / 2.0, 40.0);
+    draw_rectangle(
+        pos.x - 10.0,
+        pos.y - dims.height - 10.0,
+        dims.width + 20.0,
+        dims.height + 20.0,
+        Color::new(1.0, 1.0, 0.8, 0.95),
     );
-    for sexprec in &update.sexprecs {
-        if update.globals.is_global(sexprec.address) {
-            let addr = update.globals.fmt_ptr(sexprec.address);
-            nodes.get_mut(&addr).unwrap().pos = global_pos;
-            global_pos.y += BOX_HEIGHT + BOX_GAP;
+    draw_text(text, pos.x, pos.y, font_size, BLACK);
+}
+
+/// Seconds per frame spent running the force-directed layout.
+const FORCE_BUDGET: f64 = 0.008;
+
+/// `over_ui` means the mouse is over a window which handles its own clicks.
+fn handle_mouse(state: &mut State, over_ui: bool) {
+    let cur_mouse_pos: Vec2 = mouse_position().into();
+
+    let over_ui = over_ui || state.selected.is_some() && inspector_rect().contains(cur_mouse_pos);
+
+    if is_mouse_button_pressed(MouseButton::Left) && !over_ui {
+        state.press_pos = cur_mouse_pos;
+        // Dragging a node moves it, dragging empty space pans.
+        state.drag = match node_at(state, cur_mouse_pos)
+            .filter(|_| toggle_at(state, cur_mouse_pos).is_none())
+        {
+            Some(sexp) => {
+                let grab = state.camera.screen_to_world(cur_mouse_pos) - state.positions[&sexp];
+                Some(Drag::Node { sexp, grab })
+            }
+            None => Some(Drag::Pan),
+        };
+    }
+    if is_mouse_button_released(MouseButton::Left) {
+        // Barely moving still counts as a click, hands shake.
+        let click = state.drag.is_some() && (cur_mouse_pos - state.press_pos).length() < 5.0;
+        if click {
+            match toggle_at(state, cur_mouse_pos) {
+                Some(sexp) => state.toggle_collapsed(sexp),
+                None => state.selected = node_at(state, cur_mouse_pos),
+            }
+        }
+        state.drag = None;
+    }
+    if is_mouse_button_pressed(MouseButton::Right) && !over_ui {
+        // Give the node back to the layout.
+        if let Some(sexp) = node_at(state, cur_mouse_pos) {
+            if state.manual.remove(&sexp).is_some() {
+                state.relayout();
+            }
         }
     }
 
-    let state = State::new(update.globals, update.sexprecs, nodes);
+    match state.drag {
+        Some(Drag::Pan) => {
+            // Would be nice to grab mouse here and teleport it to the other side
+            // if it hits an edge, like in blender.
+            // Unfortunately, set_cursor_grab changes sensitivity
+            // and i don't see any way to change mouse position anyway.
+            state.camera.pan(cur_mouse_pos - state.prev_mouse_pos);
+        }
+        Some(Drag::Node { sexp, grab }) => {
+            // Clicking without moving selects, it shouldn't pin the node.
+            let pos = state.camera.screen_to_world(cur_mouse_pos) - grab;
+            match state.positions.get_mut(&sexp) {
+                Some(current) if *current != pos => {
+                    *current = pos;
+                    state.manual.insert(sexp, pos);
+                    state.routes = None;
+                    // The rest of the graph reacts to the moved node.
+                    state.force.wake();
+                }
+                Some(_) => {}
+                // Hidden by an update or a relayout while dragging.
+                None => state.drag = None,
+            }
+        }
+        None => {}
+    }
+    state.prev_mouse_pos = cur_mouse_pos;
+}
+
+/// The node under the given screen position.
+fn node_at(state: &State, screen_pos: Vec2) -> Option<Sexp> {
+    let pos = state.camera.screen_to_world(screen_pos);
+    // Later boxes are drawn on top.
     state
+        .view()
+        .sexprecs()
+        .iter()
+        .rev()
+        .map(|sexprec| sexprec.address)
+        .find(|sexp| match state.positions.get(sexp) {
+            Some(corner) => Rect::new(corner.x, corner.y, BOX_WIDTH, BOX_HEIGHT).contains(pos),
+            None => false,
+        })
 }
 
-// Macroquad decided to roll its own math lib. Predictably, it's bad.
-// There's no way to const init a Vec2 because none of its ctors are marked const.
-const BOX_INIT_X: f32 = 500.0;
-const BOX_INIT_Y: f32 = 50.0;
-const BOX_WIDTH: f32 = 950.0;
-const BOX_HEIGHT: f32 = 290.0;
-const BOX_GAP: f32 = 100.0;
-
-/// Returns the size of the subtree plus a gap - the bottom right cooordinate of the "margin".
-fn walk(nodes: &mut HashMap<String, Node>, current_addr: String, pos: Vec2) -> Vec2 {
-    let current = nodes.get_mut(&current_addr).unwrap();
-
-    if current.pos != Vec2::ZERO {
-        // Already visited
-        return Vec2::ZERO;
-    }
-    if current_addr.ends_with(')') {
-        // Global
-        // TODO this is really ugly
-        return Vec2::ZERO;
-    }
-    current.pos = pos;
-    println!("walking {} pos {}", current_addr, pos);
-    let mut child_pos = vec2(pos.x, pos.y + BOX_HEIGHT + BOX_GAP);
-    let mut margin = vec2(pos.x + BOX_WIDTH + BOX_GAP, pos.y + BOX_HEIGHT + BOX_GAP);
-    // clone for borrowck
-    for link in current.links.clone() {
-        let child_margin = walk(nodes, link.dest_addr, child_pos);
-        child_pos.x = child_pos.x.max(child_margin.x); // Max because child might return [0, 0]
-        margin = margin.max(child_margin);
-    }
-    // LATER might be cleaner to return width, height instead of global pos
-    // FIXME also doc comment wrong
-    margin
+/// The node whose collapse toggle is under the given screen position.
+fn toggle_at(state: &State, screen_pos: Vec2) -> Option<Sexp> {
+    let pos = state.camera.screen_to_world(screen_pos);
+    state
+        .positions
+        .iter()
+        .find(|(&sexp, &corner)| state.collapsible(sexp) && toggle_rect(corner).contains(pos))
+        .map(|(&sexp, _)| sexp)
 }
 
-fn draw_initial_box() {
+fn draw_initial_box(skins: &mut Skins) {
     draw_box(
+        skins,
         0,
         vec2(100.0, 100.0),
         vec2(500.0, 50.0),
         "<waiting for input from visualizr>",
+        1.0,
+        WHITE,
+        None,
     );
 }
 
-fn draw_tree(state: &mut State) {
-    for sexprec in &state.sexprecs {
-        let addr = state.globals.fmt_ptr(sexprec.address);
-        let node = &state.nodes[&addr];
-
-        let text = SexpFormatter(&state.globals, sexprec).to_string();
-        draw_box(
-            sexprec.address.0,
-            node.pos + state.offset,
-            vec2(BOX_WIDTH, BOX_HEIGHT),
-            &text,
+fn draw_report_box(skins: &mut Skins, report: &str) {
+    draw_box(
+        skins,
+        0,
+        vec2(100.0, 100.0),
+        vec2(BOX_WIDTH, BOX_HEIGHT),
+        &format!("<received an invalid update>\n{}", report),
+        1.0,
+        WHITE,
+        Some(RED),
+    );
+}
+
+fn draw_tree(state: &mut State, skins: &mut Skins, scheme: ColorScheme, note: &mut NoteEditor) {
+    let camera = state.camera;
+    let detail = Detail::from_zoom(camera.zoom);
+
+    // Edges first so they don't cover nodes which aren't editboxes.
+    let view = state.semantic.as_ref().unwrap_or(&state.graph);
+    let routes = state
+        .routes
+        .get_or_insert_with(|| route_edges(view, &state.positions));
+    for route in routes.iter() {
+        draw_route(route, &camera);
+    }
+
+    let size = vec2(BOX_WIDTH, BOX_HEIGHT) * camera.zoom;
+    for sexprec in view.sexprecs() {
+        let world_pos = match state.positions.get(&sexprec.address) {
+            Some(&pos) => pos,
+            None => continue, // Hidden
+        };
+        let pos = camera.world_to_screen(world_pos);
+        if !on_screen(pos, size) {
+            continue;
+        }
+
+        if state.collapsible(sexprec.address) {
+            let rect = toggle_rect(world_pos);
+            let corner = camera.world_to_screen(rect.point());
+            let rect = Rect::new(
+                corner.x,
+                corner.y,
+                rect.w * camera.zoom,
+                rect.h * camera.zoom,
+            );
+            let collapsed = state.collapsed.contains(&sexprec.address);
+            let hidden = state.badges.get(&sexprec.address).copied().unwrap_or(0);
+            draw_toggle(rect, collapsed, hidden);
+        }
+
+        let frame = if state.selected == Some(sexprec.address) {
+            Some(MAGENTA)
+        } else {
+            frame_color(sexprec, &state.shared).map(to_macroquad)
+        };
+        let status = state
+            .diff
+            .as_ref()
+            .and_then(|diff| diff.statuses.get(&sexprec.address));
+        let color = match status {
+            Some(status) => diff_color(status),
+            None => scheme.color(&state.graph, sexprec),
+        };
+        let (color, background) = (to_macroquad(color), to_macroquad(pale(color)));
+        // Changed fields go first so they're not cut off.
+        let changes: Vec<String> = match status {
+            Some(Status::Changed(changes)) => changes
+                .iter()
+                .map(|change| format!("{}: {} → {}\n", change.field, change.old, change.new))
+                .collect(),
+            _ => Vec::new(),
+        };
+        match detail {
+            Detail::Chip => draw_chip(&title(&state.graph, sexprec), pos, size, color, frame),
+            Detail::Summary => {
+                let text = node_text(&state.graph, state.semantic.is_some(), sexprec, detail);
+                let text = changes.concat() + &text;
+                draw_summary(
+                    &text,
+                    changes.len(),
+                    pos,
+                    size,
+                    camera.zoom,
+                    background,
+                    frame,
+                );
+            }
+            Detail::Full => {
+                let text = node_text(&state.graph, state.semantic.is_some(), sexprec, detail);
+                let text = changes.concat() + &text;
+                draw_box(
+                    skins,
+                    sexprec.address.0,
+                    pos,
+                    size,
+                    &text,
+                    camera.zoom,
+                    background,
+                    frame,
+                );
+            }
+        }
+
+        if let Some(count) = state.graph.ref_count(sexprec.address) {
+            let in_degree = state.graph.incoming(sexprec.address).len();
+            draw_ref_count(pos, size, camera.zoom, in_degree, count);
+        }
+
+        let labels = node_labels(
+            &state.graph,
+            &state.shared,
+            &state.annotations,
+            sexprec.address,
         );
+        if !labels.is_empty() {
+            let font_size = label_font_size(camera.zoom);
+            draw_text(
+                &labels.join(" | "),
+                pos.x,
+                pos.y - font_size / 3.0,
+                font_size,
+                BLACK,
+            );
+        }
     }
 
-    for (_addr, node) in &state.nodes {
-        for link in &node.links {
-            if link.dest_global {
-                continue;
+    if let Some(selected) = state.selected {
+        match draw_inspector(
+            &state.graph,
+            selected,
+            state.semantic.is_some(),
+            state.diff.as_ref(),
+            state.annotations.get(&selected).map(String::as_str),
+        ) {
+            Some(InspectorAction::Jump(sexp)) => state.jump_to(sexp),
+            Some(InspectorAction::EditNote) => {
+                note.edit(selected, state.annotations.get(&selected))
             }
-            let src = match link.link_type {
-                // TODO consts
-                LinkType::Attrib => node.pos + vec2(0.0, 200.0),
-                LinkType::Payload(i) => {
-                    let offset_x = 10.0 + 300.0 * i as f32;
-                    node.pos + vec2(offset_x, BOX_HEIGHT)
-                }
-            };
-            let dest = state.nodes[&link.dest_addr].pos;
-            draw_connection(state, src, dest);
+            Some(InspectorAction::Close) => state.selected = None,
+            None => {}
         }
     }
+
+    if !state.report.is_ok() {
+        let text = ReportFormatter(state.graph.globals(), &state.report).to_string();
+        let size = vec2(BOX_WIDTH, BOX_HEIGHT);
+        // Left of the inspector if it's open.
+        let right = if state.selected.is_some() {
+            inspector_rect().x
+        } else {
+            screen_width()
+        };
+        let pos = vec2(right - size.x - 10.0, 10.0);
+        draw_box(skins, 1, pos, size, &text, 1.0, WHITE, Some(RED));
+    }
 }
 
-fn draw_box(id: u64, box_pos: Vec2, box_size: Vec2, text: &str) {
+/// Boxes can have a colored frame to highlight them,
+/// e.g. red for nodes we couldn't fully decode.
+///
+/// Position and size are in screen coordinates, `scale` only affects the text.
+#[allow(clippy::too_many_arguments)]
+fn draw_box(
+    skins: &mut Skins,
+    id: u64,
+    box_pos: Vec2,
+    box_size: Vec2,
+    text: &str,
+    scale: f32,
+    background: Color,
+    frame: Option<Color>,
+) {
     // Don't draw if out of bounds.
     // LATER Does this actually affect perf?
-    if box_pos.x + box_size.x < 0.0
-        || box_pos.y + box_size.y < 0.0
-        || box_pos.x > screen_width()
-        || box_pos.y > screen_height()
-    {
+    if !on_screen(box_pos, box_size) {
         return;
     }
 
+    if let Some(color) = frame {
+        // The UI is drawn on top of everything else so the frame has to be outside the box.
+        let thickness = 4.0;
+        draw_rectangle_lines(
+            box_pos.x - thickness,
+            box_pos.y - thickness,
+            box_size.x + 2.0 * thickness,
+            box_size.y + 2.0 * thickness,
+            thickness,
+            color,
+        );
+    }
+
     // We wanna allow copying the data (especially stuff like pointers) but not editing
     // so we use an Editbox but reset the text every frame.
     // There seems to be no proper/native way to allow copying from a Label
     // or disable editing in an Editbox.
+    let skin = skins.get(scale, background);
+    root_ui().push_skin(skin);
     Group::new(hash!() + id, box_size)
         .position(box_pos)
         .ui(&mut root_ui(), |ui| {
             let mut text = text.to_owned();
             ui.editbox(hash!() + id, box_size - vec2(5.0, 5.0), &mut text);
         });
+    root_ui().pop_skin();
 }
 
-fn draw_connection(state: &State, src: Vec2, dest: Vec2) {
-    let src = src + state.offset;
-    let dest = dest + state.offset;
-
-    // Direct - for debugging
-    draw_line(src.x, src.y, dest.x, dest.y, 1.0, BLUE);
+/// Macroquad's UI has no way to scale widgets so we make a skin for every font size
+/// and background color we need.
+///
+/// Font sizes are integers and colors come from the legends so there's only a limited number of them.
+#[derive(Default)]
+struct Skins {
+    cache: HashMap<(u16, [u8; 4]), Skin>,
+}
 
-    // Do some sane routing - TODO
-    //draw_line(src.x, src.y, src.x, dest.y, 1.0, GREEN);
-    //draw_line(src.x, dest.y, dest.x, dest.y, 1.0, GREEN);
+impl Skins {
+    fn get(&mut self, scale: f32, background: Color) -> &Skin {
+        let font_size = (FULL_FONT_SIZE * scale).round().max(1.0) as u16;
+        let key = (font_size, background.into());
+        self.cache.entry(key).or_insert_with(|| {
+            let ui = root_ui();
+            let editbox_style = ui
+                .style_builder()
+                .font_size(font_size)
+                .text_color(BLACK)
+                .color(background)
+                .color_hovered(background)
+                .color_clicked(background)
+                .color_selected(Color::from_rgba(200, 200, 200, 255))
+                .build();
+            Skin {
+                editbox_style,
+                ..ui.default_skin()
+            }
+        })
+    }
 }
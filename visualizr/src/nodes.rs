@@ -0,0 +1,110 @@
+use commonr::graph::RefCount;
+use macroquad::prelude::*;
+use renderr::nodes::{ref_count_font_size, ref_count_label, SUMMARY_FONT_SIZE};
+
+use crate::colors::to_macroquad;
+
+/// Whether any part of the rectangle (screen coordinates) is visible.
+#[must_use]
+pub fn on_screen(pos: Vec2, size: Vec2) -> bool {
+    pos.x + size.x >= 0.0
+        && pos.y + size.y >= 0.0
+        && pos.x <= screen_width()
+        && pos.y <= screen_height()
+}
+
+/// `text` is usually the type, see `title`.
+pub fn draw_chip(text: &str, pos: Vec2, size: Vec2, color: Color, frame: Option<Color>) {
+    draw_rectangle(pos.x, pos.y, size.x, size.y, color);
+    draw_frame(pos, size, frame.unwrap_or(DARKGRAY));
+
+    // Big enough to read from far away, small enough that long names like WEAKREFSXP fit.
+    let font_size = size.y * 0.4;
+    if font_size >= 4.0 {
+        let dims = measure_text(text, None, font_size as u16, 1.0);
+        draw_text(
+            text,
+            pos.x + (size.x - dims.width) / 2.0,
+            pos.y + (size.y + dims.height) / 2.0,
+            font_size,
+            BLACK,
+        );
+    }
+}
+
+/// Plain text, cut off at the bottom of the box.
+///
+/// The first `highlighted` lines are red - used for fields which changed since the snapshot
+/// we're comparing to.
+pub fn draw_summary(
+    text: &str,
+    highlighted: usize,
+    pos: Vec2,
+    size: Vec2,
+    zoom: f32,
+    background: Color,
+    frame: Option<Color>,
+) {
+    draw_rectangle(pos.x, pos.y, size.x, size.y, background);
+    draw_frame(pos, size, frame.unwrap_or(DARKGRAY));
+
+    let font_size = SUMMARY_FONT_SIZE * zoom;
+    let mut y = pos.y + font_size;
+    for (i, line) in text.lines().enumerate() {
+        if y > pos.y + size.y {
+            break;
+        }
+        let color = if i < highlighted { RED } else { BLACK };
+        draw_text(line, pos.x + font_size / 3.0, y, font_size, color);
+        y += font_size;
+    }
+}
+
+/// How many references point to the node and how that compares to `named`,
+/// under the bottom right corner of the box. Mismatches are colored.
+pub fn draw_ref_count(pos: Vec2, size: Vec2, zoom: f32, in_degree: usize, count: RefCount) {
+    let font_size = ref_count_font_size(zoom);
+    if font_size < 6.0 {
+        return;
+    }
+    let (text, color) = ref_count_label(in_degree, count);
+    let color = to_macroquad(color);
+    let dims = measure_text(&text, None, font_size as u16, 1.0);
+    draw_text(
+        &text,
+        pos.x + size.x - dims.width,
+        pos.y + size.y + font_size,
+        font_size,
+        color,
+    );
+}
+
+fn draw_frame(pos: Vec2, size: Vec2, color: Color) {
+    draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 4.0, color);
+}
+
+/// `rect` is in screen coordinates, `hidden` is how many nodes collapsing hid.
+pub fn draw_toggle(rect: Rect, collapsed: bool, hidden: usize) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, WHITE);
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, DARKGRAY);
+    let font_size = rect.h;
+    let symbol = if collapsed { "+" } else { "-" };
+    let dims = measure_text(symbol, None, font_size as u16, 1.0);
+    draw_text(
+        symbol,
+        rect.x + (rect.w - dims.width) / 2.0,
+        rect.y + (rect.h + dims.height) / 2.0,
+        font_size,
+        BLACK,
+    );
+
+    if collapsed && hidden > 0 {
+        let badge = format!("+{} nodes", hidden);
+        let font_size = (rect.h * 0.8).max(14.0);
+        let dims = measure_text(&badge, None, font_size as u16, 1.0);
+        let x = rect.x - dims.width - 10.0;
+        let y = rect.y + (rect.h + dims.height) / 2.0;
+        draw_rectangle(x - 4.0, rect.y, dims.width + 8.0, rect.h, YELLOW);
+        draw_text(&badge, x, y, font_size, BLACK);
+    }
+}
@@ -0,0 +1,94 @@
+use commonr::data::Sexp;
+use macroquad::{
+    hash,
+    prelude::*,
+    ui::{
+        root_ui,
+        widgets::{InputText, Window},
+        Id,
+    },
+};
+
+const WIDTH: f32 = 600.0;
+const HEIGHT: f32 = 80.0;
+const LINE_HEIGHT: f32 = 22.0;
+
+/// The window for attaching a note to a node - N opens it for the selected node,
+/// enter saves the note, escape closes the window without saving.
+#[derive(Debug)]
+pub struct NoteEditor {
+    pub open: bool,
+    input_id: Id,
+    /// The node being annotated.
+    sexp: Option<Sexp>,
+    text: String,
+}
+
+impl NoteEditor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input_id: hash!(),
+            sexp: None,
+            text: String::new(),
+        }
+    }
+
+    /// Start editing the note of `sexp`, `current` is its existing note if any.
+    pub fn edit(&mut self, sexp: Sexp, current: Option<&String>) {
+        self.open = true;
+        self.sexp = Some(sexp);
+        self.text = current.cloned().unwrap_or_default();
+        root_ui().set_input_focus(self.input_id);
+    }
+
+    /// While it's open, all keyboard input goes to it.
+    pub fn handle_keys(&mut self) {
+        if self.open && is_key_pressed(KeyCode::Escape) {
+            self.close();
+        }
+    }
+
+    /// Whether the screen position is inside the window so clicks there don't go through to the graph.
+    #[must_use]
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.open && Rect::new(10.0, 10.0, WIDTH, HEIGHT).contains(pos)
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.sexp = None;
+        root_ui().clear_input_focus();
+    }
+
+    /// Returns the node and its new note when the user presses enter.
+    /// An empty note means the note should be removed.
+    pub fn draw(&mut self) -> Option<(Sexp, String)> {
+        if !self.open {
+            return None;
+        }
+
+        // The input is an editbox so enter inserts a newline.
+        let submit = self.text.contains('\n') || is_key_pressed(KeyCode::Enter);
+        self.text.retain(|c| c != '\n');
+
+        let input_id = self.input_id;
+        let text = &mut self.text;
+        Window::new(hash!(), vec2(10.0, 10.0), vec2(WIDTH, HEIGHT))
+            .label("note (enter to save, escape to cancel)")
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                InputText::new(input_id)
+                    .size(vec2(WIDTH - 20.0, LINE_HEIGHT))
+                    .ui(ui, text);
+            });
+
+        if !submit {
+            return None;
+        }
+        let note = self.sexp.map(|sexp| (sexp, self.text.trim().to_owned()));
+        self.close();
+        note
+    }
+}
@@ -0,0 +1,216 @@
+use commonr::{
+    data::{truncate, Sexp},
+    graph::ObjectGraph,
+};
+use macroquad::{
+    hash,
+    prelude::*,
+    ui::{
+        root_ui,
+        widgets::{InputText, Window},
+        Id,
+    },
+};
+
+/// How many matches to list, the rest is only counted.
+const MAX_LISTED: usize = 20;
+const WIDTH: f32 = 600.0;
+const LINE_HEIGHT: f32 = 22.0;
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub sexp: Sexp,
+    /// Why it matched, e.g. `symbol "x"`.
+    pub reason: String,
+}
+
+/// Find nodes by address, type, symbol name, string contents, global name or root name.
+///
+/// Everything is case-insensitive substring matching except types which have to match exactly
+/// (otherwise searching for "list" would also find every pairlist).
+/// Results are in the order the nodes were received.
+#[must_use]
+pub fn search(graph: &ObjectGraph, query: &str) -> Vec<Match> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    // Addresses can be written as in the boxes (@7f..) or as in gdb (0x7f..).
+    let hex = query.trim_start_matches('@').trim_start_matches("0x");
+    let is_hex = !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit());
+
+    let mut matches = Vec::new();
+    for sexprec in graph.sexprecs() {
+        let sexp = sexprec.address;
+        let reason = if is_hex && format!("{:x}", sexp.0).contains(hex) {
+            Some("address".to_owned())
+        } else if sexprec.ty.to_string().to_lowercase() == query
+            || sexprec.ty_name.to_lowercase() == query
+        {
+            Some("type".to_owned())
+        } else if let Some(name) = graph
+            .symbol_name(sexp)
+            .filter(|name| name.to_lowercase().contains(&query))
+        {
+            Some(format!("symbol {:?}", name))
+        } else if let Some(string) = graph
+            .string_value(sexp)
+            .filter(|string| string.to_lowercase().contains(&query))
+        {
+            Some(format!("string {:?}", truncate(string, 30)))
+        } else if let Some(name) = graph
+            .globals()
+            .name(sexp)
+            .filter(|name| name.to_lowercase().contains(&query))
+        {
+            Some(format!("global {}", name))
+        } else {
+            graph
+                .roots()
+                .iter()
+                .find(|root| root.sexp == sexp && root.name.to_lowercase().contains(&query))
+                .map(|root| format!("root {}", root.name))
+        };
+        if let Some(reason) = reason {
+            matches.push(Match { sexp, reason });
+        }
+    }
+    matches
+}
+
+/// The search window - Ctrl+F opens it, Escape closes it.
+#[derive(Debug)]
+pub struct SearchBar {
+    pub open: bool,
+    input_id: Id,
+    query: String,
+    /// The query `matches` was computed for.
+    searched: String,
+    matches: Vec<Match>,
+    /// Index of the highlighted match.
+    cursor: usize,
+    /// Of the window when it was last drawn.
+    height: f32,
+}
+
+impl SearchBar {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input_id: hash!(),
+            query: String::new(),
+            searched: String::new(),
+            matches: Vec::new(),
+            cursor: 0,
+            height: 0.0,
+        }
+    }
+
+    /// Open or close the bar based on keyboard input.
+    ///
+    /// While it's open, all keyboard input goes to it.
+    pub fn handle_keys(&mut self) {
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if ctrl && is_key_pressed(KeyCode::F) {
+            self.open = true;
+            root_ui().set_input_focus(self.input_id);
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+            root_ui().clear_input_focus();
+        }
+    }
+
+    /// Whether the screen position is inside the window so clicks there don't go through to the graph.
+    #[must_use]
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.open && Rect::new(10.0, 10.0, WIDTH, self.height).contains(pos)
+    }
+
+    /// Forget the results, they refer to the previous graph.
+    pub fn invalidate(&mut self) {
+        self.searched.clear();
+        self.matches.clear();
+        self.cursor = 0;
+    }
+
+    /// Returns the node to jump to, if any.
+    pub fn draw(&mut self, graph: &ObjectGraph) -> Option<Sexp> {
+        if !self.open {
+            return None;
+        }
+
+        // The input is an editbox so enter inserts a newline.
+        let mut submit = self.query.contains('\n') || is_key_pressed(KeyCode::Enter);
+        self.query.retain(|c| c != '\n');
+
+        if self.query != self.searched {
+            self.matches = search(graph, &self.query);
+            self.searched = self.query.clone();
+            self.cursor = 0;
+        }
+
+        if !self.matches.is_empty() {
+            if is_key_pressed(KeyCode::Down) {
+                self.cursor = (self.cursor + 1).min(self.matches.len().min(MAX_LISTED) - 1);
+            }
+            if is_key_pressed(KeyCode::Up) {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+        }
+
+        let listed = self.matches.len().min(MAX_LISTED);
+        let height = 90.0 + (listed + 1) as f32 * LINE_HEIGHT;
+        self.height = height;
+        let mut clicked = None;
+        let input_id = self.input_id;
+        let query = &mut self.query;
+        let matches = &self.matches;
+        let cursor = self.cursor;
+        Window::new(hash!(), vec2(10.0, 10.0), vec2(WIDTH, height))
+            .label("search (address, type, symbol, string, global)")
+            .movable(false)
+            .ui(&mut root_ui(), |ui| {
+                InputText::new(input_id)
+                    .size(vec2(WIDTH - 20.0, LINE_HEIGHT))
+                    .ui(ui, query);
+                if matches.is_empty() {
+                    if !query.is_empty() {
+                        ui.label(None, "no matches");
+                    }
+                    return;
+                }
+                for (i, m) in matches.iter().take(MAX_LISTED).enumerate() {
+                    let marker = if i == cursor { ">" } else { " " };
+                    let sexprec = graph.get(m.sexp).unwrap();
+                    let text = format!(
+                        "{} {} {} - {}",
+                        marker,
+                        sexprec.ty,
+                        graph.globals().fmt_ptr(m.sexp),
+                        m.reason
+                    );
+                    if ui.button(None, text.as_str()) {
+                        clicked = Some(i);
+                    }
+                }
+                if matches.len() > MAX_LISTED {
+                    ui.label(
+                        None,
+                        &format!("... and {} more", matches.len() - MAX_LISTED),
+                    );
+                }
+            });
+
+        if let Some(i) = clicked {
+            self.cursor = i;
+            submit = true;
+        }
+        if submit {
+            self.matches.get(self.cursor).map(|m| m.sexp)
+        } else {
+            None
+        }
+    }
+}
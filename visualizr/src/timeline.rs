@@ -0,0 +1,141 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use macroquad::prelude::*;
+use renderr::history::History;
+
+const CELL_WIDTH: f32 = 130.0;
+const CELL_HEIGHT: f32 = 50.0;
+const FONT_SIZE: f32 = 20.0;
+const MARGIN: f32 = 10.0;
+
+/// PageUp / PageDown go to the previous / next snapshot, returns whether it changed.
+pub fn handle_keys(history: &mut History) -> bool {
+    let current = history.current_index();
+    if is_key_pressed(KeyCode::PageUp) && current > 0 {
+        history.select(current - 1)
+    } else if is_key_pressed(KeyCode::PageDown) {
+        history.select(current + 1)
+    } else {
+        false
+    }
+}
+
+/// Cells shrink when there are many so the strip doesn't cover the legend.
+fn cell_width(history: &History) -> f32 {
+    (screen_width() * 0.5 / history.snapshots().len() as f32).min(CELL_WIDTH)
+}
+
+/// Where the strip is on screen, centered at the bottom.
+fn rect(history: &History) -> Rect {
+    let width = history.snapshots().len() as f32 * cell_width(history);
+    Rect::new(
+        (screen_width() - width) / 2.0,
+        screen_height() - CELL_HEIGHT - MARGIN,
+        width,
+        CELL_HEIGHT,
+    )
+}
+
+/// Whether the screen position is inside the strip so clicks there don't go through to the graph.
+#[must_use]
+pub fn contains(history: &History, pos: Vec2) -> bool {
+    // A single snapshot isn't drawn, there's nothing to switch to.
+    history.snapshots().len() > 1 && rect(history).contains(pos)
+}
+
+/// Draw the timeline strip, returns whether the user clicked another snapshot or changed the base.
+pub fn draw(history: &mut History) -> bool {
+    if history.snapshots().len() < 2 {
+        return false;
+    }
+
+    let rect = rect(history);
+    let cell_width = cell_width(history);
+    let mouse: Vec2 = mouse_position().into();
+    let mut clicked = None;
+    let mut hovered = None;
+    for (i, snapshot) in history.snapshots().enumerate() {
+        let cell = Rect::new(
+            rect.x + i as f32 * cell_width,
+            rect.y,
+            cell_width,
+            CELL_HEIGHT,
+        );
+        let background = if i == history.current_index() {
+            Color::new(0.6, 0.8, 1.0, 0.95)
+        } else if Some(i) == history.base_index() {
+            Color::new(1.0, 0.8, 0.5, 0.95)
+        } else if cell.contains(mouse) {
+            Color::new(0.9, 0.9, 0.9, 0.95)
+        } else {
+            Color::new(1.0, 1.0, 1.0, 0.9)
+        };
+        draw_rectangle(cell.x, cell.y, cell.w, cell.h, background);
+        draw_rectangle_lines(cell.x, cell.y, cell.w, cell.h, 2.0, DARKGRAY);
+        let base = if Some(i) == history.base_index() {
+            " base"
+        } else {
+            ""
+        };
+        draw_text(
+            &format!("#{}{}", snapshot.number, base),
+            cell.x + 6.0,
+            cell.y + FONT_SIZE,
+            FONT_SIZE,
+            BLACK,
+        );
+        if cell_width >= CELL_WIDTH {
+            draw_text(
+                &format_time(snapshot.received),
+                cell.x + 6.0,
+                cell.y + 2.0 * FONT_SIZE,
+                FONT_SIZE,
+                BLACK,
+            );
+        }
+
+        if cell.contains(mouse) {
+            hovered = Some(snapshot);
+            if is_mouse_button_pressed(MouseButton::Left) {
+                clicked = Some(i);
+            }
+        }
+    }
+
+    if let Some(snapshot) = hovered {
+        let text = format!(
+            "#{} at {} from pid {}, {} nodes, {} roots",
+            snapshot.number,
+            format_time(snapshot.received),
+            snapshot.update.pid,
+            snapshot.update.sexprecs.len(),
+            snapshot.update.roots.len()
+        );
+        draw_text(&text, rect.x, rect.y - MARGIN, FONT_SIZE, BLACK);
+    }
+
+    let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+    match clicked {
+        // Shift+click picks what to compare to.
+        Some(i) if shift => {
+            history.set_base(i);
+            true
+        }
+        Some(i) => history.select(i),
+        None => false,
+    }
+}
+
+/// Time of day in UTC - good enough to tell snapshots apart without a date/time dependency.
+fn format_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}